@@ -0,0 +1,117 @@
+//! accessibility checks for debug builds
+//!
+//! `enable_audit` watches the document and warns about common accessibility problems of elements as they are attached,
+//! together with their `data-location`.
+
+use crate::{prelude::*, dom_events::has_handler};
+use std::collections::HashMap;
+
+struct Audit {
+	_observer: web_sys::MutationObserver,
+	_callback: Closure<dyn FnMut(js_sys::Array)>,
+}
+
+// elements that can be focused and activated from the keyboard without any help
+const INTERACTIVE_ELEMENTS: &[&str] = &["a", "button", "input", "select", "textarea", "summary", "option", "label", "details"];
+
+fn warn(element: &web_sys::Element, problem: &str) {
+	let location = element.get_attribute("data-location").unwrap_or_else(|| "unknown location".to_owned());
+	log::warn!("[a11y] {problem} at {location}");
+}
+
+fn has_accessible_name(element: &web_sys::Element) -> bool {
+	["aria-label", "aria-labelledby", "title"].iter().any(|&x| element.get_attribute(x).is_some_and(|x| !x.trim().is_empty())) ||
+	element.text_content().is_some_and(|x| !x.trim().is_empty()) ||
+	element.query_selector("img[alt]:not([alt=''])").ok().flatten().is_some()
+}
+
+fn has_label(element: &web_sys::Element) -> bool {
+	let labels = if let Some(x) = element.dyn_ref::<web_sys::HtmlInputElement>() {
+		if matches!(x.type_().as_str(), "hidden" | "submit" | "reset" | "button" | "image") { return true; }
+		x.labels()
+	} else if let Some(x) = element.dyn_ref::<web_sys::HtmlSelectElement>() {
+		Some(x.labels())
+	} else if let Some(x) = element.dyn_ref::<web_sys::HtmlTextAreaElement>() {
+		Some(x.labels())
+	} else {
+		return true;
+	};
+
+	labels.is_some_and(|x| x.length() > 0) || element.has_attribute("aria-label") || element.has_attribute("aria-labelledby")
+}
+
+fn audit_element(element: &web_sys::Element, ids: &HashMap<String, u32>) {
+	let tag = element.tag_name().to_ascii_lowercase();
+	match tag.as_str() {
+		"img" if !element.has_attribute("alt") => warn(element, "<img> without alt, use an empty alt for decorative images"),
+		"button" if !has_accessible_name(element) => warn(element, "<button> without an accessible name"),
+		"a" if element.has_attribute("href") && !has_accessible_name(element) => warn(element, "<a> without an accessible name"),
+		"input" | "select" | "textarea" if !has_label(element) => warn(element, &format!("<{tag}> without a label, add a <label> with `for_ctrl` pointing at its id")),
+		_ => {},
+	}
+
+	if let Some(tabindex) = element.get_attribute("tabindex").and_then(|x| x.trim().parse::<i32>().ok()).filter(|&x| x > 0) {
+		warn(element, &format!("positive tabindex {tabindex}, it breaks the tab order of the page"));
+	}
+
+	if let Some(id) = element.get_attribute(web_str::id()).filter(|x| ids.get(x).is_some_and(|&count| count > 1)) {
+		warn(element, &format!("duplicate id {id:?}"));
+	}
+
+	let Some(entity) = Entity::from_node(element) else { return; };
+	if INTERACTIVE_ELEMENTS.contains(&tag.as_str()) || (element.has_attribute("role") && element.has_attribute("tabindex")) { return; }
+	if has_handler(entity, web_str::click()) && ![web_str::keydown(), web_str::keyup(), web_str::keypress()].iter().any(|x| has_handler(entity, x)) {
+		warn(element, &format!("<{tag}> handles clicks, but not the keyboard, use a <button> or give it a role, a tabindex and a keyboard handler"));
+	}
+}
+
+fn id_counts(document: &web_sys::Document) -> HashMap<String, u32> {
+	let mut ids = HashMap::<String, u32>::new();
+	let with_id = document.query_selector_all("[id]").expect("can't query elements");
+	for element in (0..with_id.length()).filter_map(|i| with_id.get(i)) {
+		if let Some(id) = element.unchecked_ref::<web_sys::Element>().get_attribute(web_str::id()) { *ids.entry(id).or_default() += 1; }
+	}
+	ids
+}
+
+// elements in `audited` were already checked, so moving them around doesn't repeat the warnings
+fn audit(root: &web_sys::Element, ids: &HashMap<String, u32>, audited: &js_sys::WeakSet) {
+	if audited.has(root) { return; }
+	audited.add(root);
+	audit_element(root, ids);
+	let descendants = root.query_selector_all("*").expect("can't query elements");
+	for element in (0..descendants.length()).filter_map(|i| descendants.get(i)) {
+		if audited.has(&element) { continue; }
+		audited.add(&element);
+		audit_element(element.unchecked_ref(), ids);
+	}
+}
+
+/// Check the document and every element attached to it afterwards for accessibility problems, they are logged as warnings
+///
+/// Does nothing in release builds and on the server.
+pub fn enable_audit() {
+	if cfg!(not(debug_assertions)) || crate::ssr::ENABLED || Audit::try_resource().is_some() { return; }
+
+	let document = web_sys::window().expect("no window").document().expect("no document");
+	let audited = js_sys::WeakSet::new();
+	let callback = Closure::<dyn FnMut(js_sys::Array)>::new({
+		let document = document.clone();
+		let audited = audited.clone();
+		move |records: js_sys::Array| {
+			let mut ids = None;
+			for record in records.iter() {
+				let nodes = record.unchecked_into::<web_sys::MutationRecord>().added_nodes();
+				for node in (0..nodes.length()).filter_map(|i| nodes.get(i)) {
+					// a batch can add a lot of elements, so the ids are only counted once
+					if let Some(element) = node.dyn_ref::<web_sys::Element>() { audit(element, ids.get_or_insert_with(|| id_counts(&document)), &audited); }
+				}
+			}
+		}
+	});
+	let observer = web_sys::MutationObserver::new(callback.as_ref().unchecked_ref()).expect("can't create mutation observer");
+	observer.observe_with_options(&document, web_sys::MutationObserverInit::new().child_list(true).subtree(true)).expect("can't observe the document");
+	if let Some(root) = document.document_element() { audit(&root, &id_counts(&document), &audited); }
+
+	Audit { _observer: observer, _callback: callback }.register_resource();
+}
@@ -0,0 +1,222 @@
+//! imperative animations with the Web Animations API
+//!
+//! Keyframes are made of the same properties as classes and styles:
+//!
+//! ```ignore
+//! let shake = input.animate(
+//!     [
+//!         Keyframe::new(css::transform::translate_x_px(0)),
+//!         Keyframe::new(css::transform::translate_x_px(-8)).offset(0.25),
+//!         Keyframe::new(css::transform::translate_x_px(8)).offset(0.75),
+//!         Keyframe::new(css::transform::translate_x_px(0)),
+//!     ],
+//!     &AnimationOptions::default().duration(300.),
+//! );
+//! shake.finished().await;
+//! ```
+//!
+//! Animations of an element are cancelled when its entity is removed. Nothing is animated on the server.
+
+use crate::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+	// `Element.animate()` and `Animation` are behind `web_sys_unstable_apis`
+	type Animatable;
+
+	#[wasm_bindgen(method)]
+	fn animate(this: &Animatable, keyframes: &js_sys::Array, options: &js_sys::Object) -> Animation;
+
+	#[derive(Clone)]
+	type Animation;
+
+	#[wasm_bindgen(method)] fn play(this: &Animation);
+	#[wasm_bindgen(method)] fn pause(this: &Animation);
+	#[wasm_bindgen(method)] fn reverse(this: &Animation);
+	#[wasm_bindgen(method)] fn cancel(this: &Animation);
+	#[wasm_bindgen(method)] fn finish(this: &Animation);
+	#[wasm_bindgen(method, getter)] fn finished(this: &Animation) -> js_sys::Promise;
+	#[wasm_bindgen(method, getter, js_name = playState)] fn play_state(this: &Animation) -> String;
+	#[wasm_bindgen(method, getter, js_name = playbackRate)] fn playback_rate(this: &Animation) -> f64;
+	#[wasm_bindgen(method, js_name = updatePlaybackRate)] fn update_playback_rate(this: &Animation, rate: f64);
+}
+
+/// Properties at some point of an animation, keyframes without an offset are spaced evenly
+#[derive(Debug, Clone, Default)]
+pub struct Keyframe {
+	properties: Vec<css::Property>,
+	offset: Option<f64>,
+	easing: Option<String>,
+}
+
+impl Keyframe {
+	pub fn new(properties: impl AppendProperty) -> Self {
+		let mut res = Self::default();
+		properties.append_property(&mut res.properties);
+		res
+	}
+
+	/// Where the keyframe is, from 0 to 1
+	#[must_use] pub fn offset(self, offset: f64) -> Self { Self { offset: Some(offset), ..self } }
+	/// Timing function from this keyframe to the next one, e.g. `ease-in`
+	#[must_use] pub fn easing(self, easing: impl Into<String>) -> Self { Self { easing: Some(easing.into()), ..self } }
+
+	fn to_js(&self) -> js_sys::Object {
+		let res = js_sys::Object::new();
+		for (name, value) in self.properties.iter().filter_map(declaration) {
+			js_sys::Reflect::set(&res, &name.into(), &value.into()).expect("can't set keyframe property");
+		}
+		if let Some(offset) = self.offset { js_sys::Reflect::set(&res, &"offset".into(), &offset.into()).expect("can't set keyframe offset"); }
+		if let Some(easing) = &self.easing { js_sys::Reflect::set(&res, &"easing".into(), &easing.into()).expect("can't set keyframe easing"); }
+		res
+	}
+}
+
+fn declaration(property: &css::Property) -> Option<(String, String)> {
+	let property = property.to_string();
+	let (name, value) = property.trim_end_matches(';').split_once(':')?;
+	Some((js_name(name), value.to_owned()))
+}
+
+// keyframes take properties in camelCase, like `element.style` does
+fn js_name(name: &str) -> String {
+	match name {
+		"float" => return "cssFloat".to_owned(),
+		"offset" => return "cssOffset".to_owned(),
+		name if name.starts_with("--") => return name.to_owned(),
+		_ => {},
+	}
+
+	let mut res = String::with_capacity(name.len());
+	let mut upper = false;
+	for c in name.chars() {
+		if c == '-' { upper = !res.is_empty(); continue; }
+		res.push(if upper { c.to_ascii_uppercase() } else { c });
+		upper = false;
+	}
+	res
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction { #[default] Normal, Reverse, Alternate, AlternateReverse }
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Fill { #[default] None, Forwards, Backwards, Both }
+
+/// Timing of an animation, times are in milliseconds
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationOptions {
+	pub duration: f64,
+	pub delay: f64,
+	pub end_delay: f64,
+	/// `f64::INFINITY` repeats forever
+	pub iterations: f64,
+	pub direction: Direction,
+	/// whether the first keyframe applies during `delay` and the last one after the animation
+	pub fill: Fill,
+	pub easing: String,
+}
+
+impl Default for AnimationOptions {
+	fn default() -> Self {
+		Self { duration: 0., delay: 0., end_delay: 0., iterations: 1., direction: Direction::Normal, fill: Fill::None, easing: "linear".to_owned() }
+	}
+}
+
+impl AnimationOptions {
+	#[must_use] pub fn duration(self, duration: f64) -> Self { Self { duration, ..self } }
+	#[must_use] pub fn delay(self, delay: f64) -> Self { Self { delay, ..self } }
+	#[must_use] pub fn end_delay(self, end_delay: f64) -> Self { Self { end_delay, ..self } }
+	#[must_use] pub fn iterations(self, iterations: f64) -> Self { Self { iterations, ..self } }
+	#[must_use] pub fn infinite(self) -> Self { Self { iterations: f64::INFINITY, ..self } }
+	#[must_use] pub fn direction(self, direction: Direction) -> Self { Self { direction, ..self } }
+	#[must_use] pub fn fill(self, fill: Fill) -> Self { Self { fill, ..self } }
+	#[must_use] pub fn easing(self, easing: impl Into<String>) -> Self { Self { easing: easing.into(), ..self } }
+
+	fn to_js(&self) -> js_sys::Object {
+		let direction = match self.direction {
+			Direction::Normal => "normal",
+			Direction::Reverse => "reverse",
+			Direction::Alternate => "alternate",
+			Direction::AlternateReverse => "alternate-reverse",
+		};
+		let fill = match self.fill {
+			Fill::None => "none",
+			Fill::Forwards => "forwards",
+			Fill::Backwards => "backwards",
+			Fill::Both => "both",
+		};
+
+		let res = js_sys::Object::new();
+		for (key, value) in [
+			("duration", JsValue::from(self.duration)),
+			("delay", self.delay.into()),
+			("endDelay", self.end_delay.into()),
+			("iterations", self.iterations.into()),
+			("direction", direction.into()),
+			("fill", fill.into()),
+			("easing", self.easing.as_str().into()),
+		] {
+			js_sys::Reflect::set(&res, &key.into(), &value).expect("can't set animation option");
+		}
+		res
+	}
+}
+
+// cancels the element's animations when it's removed
+#[derive(Default)]
+struct Animations(Vec<Animation>);
+
+impl Drop for Animations {
+	fn drop(&mut self) {
+		for animation in &self.0 { animation.cancel(); }
+	}
+}
+
+/// A running animation, it does nothing on the server
+#[derive(Clone)]
+pub struct AnimationHandle(Option<Animation>);
+
+impl AnimationHandle {
+	pub fn play(&self) { if let Some(x) = &self.0 { x.play(); } }
+	pub fn pause(&self) { if let Some(x) = &self.0 { x.pause(); } }
+	/// Play backwards from where it is now
+	pub fn reverse(&self) { if let Some(x) = &self.0 { x.reverse(); } }
+	/// Stop and remove the animation's effects
+	pub fn cancel(&self) { if let Some(x) = &self.0 { x.cancel(); } }
+	/// Jump to the end
+	pub fn finish(&self) { if let Some(x) = &self.0 { x.finish(); } }
+	pub fn playback_rate(&self) -> f64 { self.0.as_ref().map_or(1., Animation::playback_rate) }
+	/// Change the speed without jumping, negative rates play backwards
+	pub fn set_playback_rate(&self, rate: f64) { if let Some(x) = &self.0 { x.update_playback_rate(rate); } }
+
+	/// Wait for the animation to finish, `false` if it was cancelled instead
+	pub async fn finished(&self) -> bool {
+		let Some(animation) = &self.0 else { return true; };
+		wasm_bindgen_futures::JsFuture::from(animation.finished()).await.is_ok()
+	}
+}
+
+pub(crate) fn animate(element: Element, keyframes: &[Keyframe], options: &AnimationOptions) -> AnimationHandle {
+	if element.is_dead() { log::warn!("animate dead entity {:?}", element.as_entity()); return AnimationHandle(None); }
+	if element.has_no_element("animate") { return AnimationHandle(None); }
+	if crate::ssr::ENABLED { return AnimationHandle(None); }
+
+	let keyframes = keyframes.iter().map(Keyframe::to_js).collect::<js_sys::Array>();
+	let animation = element.get_cmp::<web_sys::Element>().unchecked_ref::<Animatable>().animate(&keyframes, &options.to_js());
+
+	let mut animations = element.get_cmp_mut_or_default::<Animations>();
+	animations.0.retain(|x| !matches!(x.play_state().as_str(), "finished" | "idle"));
+	animations.0.push(animation.clone());
+	AnimationHandle(Some(animation))
+}
+
+#[test]
+fn declarations() {
+	assert_eq!(declaration(&css::Property::Opacity(css::opacity::val(0.5))), Some(("opacity".to_owned(), "0.5".to_owned())));
+
+	assert_eq!(js_name("background-color"), "backgroundColor");
+	assert_eq!(js_name("-webkit-user-select"), "webkitUserSelect");
+	assert_eq!(js_name("float"), "cssFloat");
+	assert_eq!(js_name("--accent"), "--accent");
+}
@@ -0,0 +1,251 @@
+//! typed ARIA attributes
+//!
+//! Every attribute has a setter, a builder and a `_signal` variant of both, same as classes and attributes.
+//! Typed values accept `None` to remove the attribute:
+//!
+//! ```ignore
+//! let expanded = Mutable::new(false);
+//! let menu = e::ul().role(Role::Menu);
+//! let button = e::button()
+//!     .aria_haspopup(HasPopup::Menu)
+//!     .aria_controls(&menu)
+//!     .aria_expanded_signal(expanded.signal());
+//! ```
+
+use crate::{prelude::*, element::SignalHandlesCollection};
+use futures_signals::signal::{Signal, SignalExt};
+use std::{borrow::Cow, cell::Cell};
+
+/// Something that can be the value of an ARIA attribute
+pub trait AriaValue {
+	fn aria_value(&self) -> Cow<'static, str>;
+}
+
+impl AriaValue for bool {
+	fn aria_value(&self) -> Cow<'static, str> { Cow::Borrowed(if *self { "true" } else { "false" }) }
+}
+
+macro_rules! to_string_aria_value {
+	($($t:ty),*) => {$(
+		impl AriaValue for $t {
+			fn aria_value(&self) -> Cow<'static, str> { Cow::Owned(self.to_string()) }
+		}
+	)*};
+}
+
+to_string_aria_value!(i32, u32, f64);
+
+macro_rules! aria_enum {
+	($(#[$meta:meta])* $name:ident { $($variant:ident => $value:literal),*$(,)? }) => {
+		$(#[$meta])*
+		#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+		pub enum $name { $($variant),* }
+
+		impl AriaValue for $name {
+			fn aria_value(&self) -> Cow<'static, str> {
+				Cow::Borrowed(match self { $(Self::$variant => $value),* })
+			}
+		}
+	};
+}
+
+aria_enum!(
+	/// What an element is to assistive technologies, the value of `role`
+	Role {
+		Alert => "alert", AlertDialog => "alertdialog", Application => "application", Article => "article",
+		Banner => "banner", Button => "button", Cell => "cell", Checkbox => "checkbox",
+		ColumnHeader => "columnheader", Combobox => "combobox", Complementary => "complementary", ContentInfo => "contentinfo",
+		Definition => "definition", Dialog => "dialog", Document => "document", Feed => "feed",
+		Figure => "figure", Form => "form", Grid => "grid", GridCell => "gridcell",
+		Group => "group", Heading => "heading", Img => "img", Link => "link",
+		List => "list", ListBox => "listbox", ListItem => "listitem", Log => "log",
+		Main => "main", Marquee => "marquee", Math => "math", Menu => "menu",
+		MenuBar => "menubar", MenuItem => "menuitem", MenuItemCheckbox => "menuitemcheckbox", MenuItemRadio => "menuitemradio",
+		Meter => "meter", Navigation => "navigation", None => "none", Note => "note",
+		Option => "option", Presentation => "presentation", ProgressBar => "progressbar", Radio => "radio",
+		RadioGroup => "radiogroup", Region => "region", Row => "row", RowGroup => "rowgroup",
+		RowHeader => "rowheader", ScrollBar => "scrollbar", Search => "search", SearchBox => "searchbox",
+		Separator => "separator", Slider => "slider", SpinButton => "spinbutton", Status => "status",
+		Switch => "switch", Tab => "tab", Table => "table", TabList => "tablist",
+		TabPanel => "tabpanel", Term => "term", TextBox => "textbox", Timer => "timer",
+		Toolbar => "toolbar", Tooltip => "tooltip", Tree => "tree", TreeGrid => "treegrid",
+		TreeItem => "treeitem",
+	}
+);
+
+aria_enum!(
+	/// How urgently changes of a live region are announced
+	Live { Off => "off", Polite => "polite", Assertive => "assertive" }
+);
+
+aria_enum!(
+	/// The state of a checkbox or a toggle button
+	Checked { False => "false", True => "true", Mixed => "mixed" }
+);
+
+impl From<bool> for Checked {
+	fn from(x: bool) -> Self { if x { Self::True } else { Self::False } }
+}
+
+aria_enum!(
+	/// Which item of a set is the current one
+	Current { False => "false", True => "true", Page => "page", Step => "step", Location => "location", Date => "date", Time => "time" }
+);
+
+aria_enum!(
+	/// What kind of popup an element opens
+	HasPopup { False => "false", True => "true", Menu => "menu", ListBox => "listbox", Tree => "tree", Grid => "grid", Dialog => "dialog" }
+);
+
+aria_enum!(
+	Orientation { Horizontal => "horizontal", Vertical => "vertical" }
+);
+
+aria_enum!(
+	Sort { None => "none", Ascending => "ascending", Descending => "descending", Other => "other" }
+);
+
+thread_local! {
+	static NEXT_ID: Cell<u64> = const { Cell::new(0) };
+}
+
+// counted per render rather than taken from the entity, so that the server and `hydrate` come up with the same ids
+pub(crate) fn reset_ids() { NEXT_ID.set(0); }
+
+// ids are assigned to elements that don't have one
+fn id_of(element: &impl AsElement) -> String {
+	if let Some(id) = element.get_attr(web_str::id()) { return id; }
+	let id = format!("hobo-{}", NEXT_ID.replace(NEXT_ID.get() + 1));
+	element.set_attr(web_str::id(), &id);
+	id
+}
+
+fn set_value(element: Element, attr: &str, value: Option<impl AriaValue>) {
+	match value {
+		Some(x) => element.set_attr(attr, x.aria_value()),
+		None => element.remove_attr(attr),
+	}
+}
+
+fn set_signal<T: 'static>(element: Element, signal: impl Signal<Item = T> + 'static, f: impl Fn(Element, T) + 'static) {
+	if element.is_dead() { log::warn!("aria signal dead entity {:?}", element.as_entity()); return; }
+	let (handle, fut) = futures_signals::cancelable_future(signal.for_each(move |x| {
+		f(element, x);
+		std::future::ready(())
+	}), Default::default);
+
+	crate::signals_ext::spawn(fut);
+	element.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+}
+
+macro_rules! string_attrs {
+	($($name:ident, $attr:literal;)*) => {paste::item! {$(
+		#[inline] fn [<set_ $name>]<'a>(&self, x: impl Into<Cow<'a, str>>) { self.set_attr($attr, x); }
+		#[must_use] #[inline] fn $name<'a>(self, x: impl Into<Cow<'a, str>>) -> Self { self.[<set_ $name>](x); self }
+		#[inline] fn [<set_ $name _signal>]<'a, S: Signal<Item = V> + 'static, V: Into<Cow<'a, str>>>(&self, signal: S) { self.set_attr_signal($attr, signal); }
+		#[must_use] #[inline] fn [<$name _signal>]<'a, S: Signal<Item = V> + 'static, V: Into<Cow<'a, str>>>(self, signal: S) -> Self { self.[<set_ $name _signal>](signal); self }
+	)*}};
+}
+
+macro_rules! typed_attrs {
+	($($name:ident, $attr:literal, $t:ty;)*) => {paste::item! {$(
+		#[inline] fn [<set_ $name>](&self, x: impl Into<Option<$t>>) { set_value(self.as_element(), $attr, x.into()); }
+		#[must_use] #[inline] fn $name(self, x: impl Into<Option<$t>>) -> Self { self.[<set_ $name>](x); self }
+		fn [<set_ $name _signal>]<S: Signal<Item = V> + 'static, V: Into<Option<$t>> + 'static>(&self, signal: S) {
+			set_signal(self.as_element(), signal, |element, x| set_value(element, $attr, x.into()));
+		}
+		#[must_use] fn [<$name _signal>]<S: Signal<Item = V> + 'static, V: Into<Option<$t>> + 'static>(self, signal: S) -> Self { self.[<set_ $name _signal>](signal); self }
+	)*}};
+}
+
+macro_rules! id_attrs {
+	($($name:ident, $attr:literal;)*) => {paste::item! {$(
+		#[inline] fn [<set_ $name>](&self, x: &impl AsElement) { self.set_attr($attr, id_of(x)); }
+		#[must_use] #[inline] fn $name(self, x: &impl AsElement) -> Self { self.[<set_ $name>](x); self }
+		fn [<set_ $name _signal>]<S: Signal<Item = Option<E>> + 'static, E: AsElement + 'static>(&self, signal: S) {
+			set_signal(self.as_element(), signal, |element, x| match x {
+				Some(x) => element.set_attr($attr, id_of(&x)),
+				None => element.remove_attr($attr),
+			});
+		}
+		#[must_use] fn [<$name _signal>]<S: Signal<Item = Option<E>> + 'static, E: AsElement + 'static>(self, signal: S) -> Self { self.[<set_ $name _signal>](signal); self }
+	)*}};
+}
+
+/// `role` and `aria-*` attributes, elements referenced by `aria_controls` and the like get an id if they don't have one
+pub trait Aria: AsElement {
+	typed_attrs! {
+		role, "role", Role;
+		aria_expanded, "aria-expanded", bool;
+		aria_hidden, "aria-hidden", bool;
+		aria_disabled, "aria-disabled", bool;
+		aria_selected, "aria-selected", bool;
+		aria_checked, "aria-checked", Checked;
+		aria_pressed, "aria-pressed", Checked;
+		aria_current, "aria-current", Current;
+		aria_haspopup, "aria-haspopup", HasPopup;
+		aria_live, "aria-live", Live;
+		aria_atomic, "aria-atomic", bool;
+		aria_busy, "aria-busy", bool;
+		aria_modal, "aria-modal", bool;
+		aria_invalid, "aria-invalid", bool;
+		aria_required, "aria-required", bool;
+		aria_readonly, "aria-readonly", bool;
+		aria_multiselectable, "aria-multiselectable", bool;
+		aria_orientation, "aria-orientation", Orientation;
+		aria_sort, "aria-sort", Sort;
+		aria_level, "aria-level", u32;
+		aria_posinset, "aria-posinset", u32;
+		aria_setsize, "aria-setsize", i32;
+		aria_valuenow, "aria-valuenow", f64;
+		aria_valuemin, "aria-valuemin", f64;
+		aria_valuemax, "aria-valuemax", f64;
+	}
+
+	string_attrs! {
+		aria_label, "aria-label";
+		aria_description, "aria-description";
+		aria_roledescription, "aria-roledescription";
+		aria_valuetext, "aria-valuetext";
+	}
+
+	id_attrs! {
+		aria_controls, "aria-controls";
+		aria_labelledby, "aria-labelledby";
+		aria_describedby, "aria-describedby";
+		aria_owns, "aria-owns";
+		aria_activedescendant, "aria-activedescendant";
+		aria_errormessage, "aria-errormessage";
+	}
+}
+
+impl<T: AsElement> Aria for T {}
+
+#[test]
+fn sets_attributes() {
+	use crate::create as e;
+
+	let menu = e::ul().role(Role::Menu);
+	let expanded = futures_signals::signal::Mutable::new(false);
+	let button = e::button().aria_controls(&menu).aria_expanded_signal(expanded.signal()).aria_pressed(None);
+	expanded.set(true);
+	crate::ssr::run_pending();
+
+	assert_eq!(menu.get_attr("role").as_deref(), Some("menu"));
+	assert_eq!(button.get_attr("aria-controls"), menu.get_attr("id"));
+	assert_eq!(button.get_attr("aria-expanded").as_deref(), Some("true"));
+	assert_eq!(button.get_attr("aria-pressed"), None);
+}
+
+#[test]
+fn ids_are_counted_per_render() {
+	use crate::create as e;
+
+	let page = || {
+		let label = e::span().text("name");
+		e::div().child(label).child(e::input().aria_labelledby(&label))
+	};
+	let first = crate::ssr::render_to_string(page());
+	assert!(first.contains(r#"<span id="hobo-0">"#) && first.contains(r#"aria-labelledby="hobo-0""#));
+	assert_eq!(crate::ssr::render_to_string(page()), first);
+}
@@ -15,6 +15,7 @@ pub fn dom_element<T, E>(world: &World, entity: T, element: &E) where
 	E: AsRef<web_sys::Node> + AsRef<web_sys::Element> + AsRef<web_sys::EventTarget>,
 {
 	let entity = entity.as_entity();
+	entity.link_node(element.as_ref());
 	world.storage_mut::<web_sys::Node>().add(entity, (element.as_ref() as &web_sys::Node).clone());
 	world.storage_mut::<web_sys::Element>().add(entity, (element.as_ref() as &web_sys::Element).clone());
 	world.storage_mut::<web_sys::EventTarget>().add(entity, (element.as_ref() as &web_sys::EventTarget).clone());
@@ -31,7 +32,16 @@ pub fn register_handlers(world: &World) {
 		WORLD.storage_mut::<web_sys::EventTarget>().remove(entity);
 		WORLD.storage_mut::<DomTypes>().remove(entity);
 		WORLD.storage_mut::<Vec<crate::dom_events::EventHandler>>().remove(entity);
-		element.remove();
+		if !crate::custom_element::keeps_node(entity) { crate::transition::remove_node(entity, &element); }
+	});
+
+	world.storage_mut::<web_sys::Comment>().on_removed = Some(move |_, entity, comment| {
+		WORLD.storage_mut::<web_sys::Node>().remove(entity);
+		comment.remove();
+	});
+
+	world.storage_mut::<web_sys::ShadowRoot>().on_removed = Some(move |_, entity, _| {
+		crate::unregister_shadow_root(&shadow_root_name(entity));
 	});
 
 	world.storage_mut::<DomTypes>().on_removed = Some(move |_, entity, dom_types| {
@@ -41,10 +51,79 @@ pub fn register_handlers(world: &World) {
 	});
 }
 
-pub fn html_element<T: AsRef<web_sys::HtmlElement> + 'static + Clone>(element: &T) -> Entity {
+/// An entity backed by a `web_sys::Comment` - it has a `web_sys::Node`, but no `web_sys::Element`
+///
+/// Used to mark the position of dynamic children among their siblings without affecting layout, selectors or content models
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AsEntity)]
+pub struct Anchor(pub Entity);
+
+pub fn anchor(text: &str) -> Anchor {
+	if crate::ssr::ENABLED { return Anchor(crate::ssr::comment(if cfg!(debug_assertions) { text } else { "" })); }
+
+	let entity = WORLD.new_entity();
+
+	let comment = web_sys::window().expect("no window")
+		.document().expect("no document")
+		.create_comment(if cfg!(debug_assertions) { text } else { "" });
+	entity.link_node(comment.as_ref());
+	WORLD.storage_mut::<web_sys::Node>().add(entity, (comment.as_ref() as &web_sys::Node).clone());
+	WORLD.storage_mut::<web_sys::Comment>().add(entity, comment);
+
+	Anchor(entity)
+}
+
+/// An entity with ordered `Children` but no DOM node of its own
+///
+/// Children of a fragment are placed directly into the node of its nearest non-fragment ancestor,
+/// so a component can return several elements without wrapping them in an extra `div`.
+/// Fragments have no attributes, classes or styles, methods for those log a warning and do nothing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AsEntity)]
+pub struct Fragment(pub Entity);
+
+impl AsElement for Fragment { }
+
+pub(crate) struct FragmentMarker;
+
+pub fn fragment() -> Fragment {
+	let entity = WORLD.new_entity();
+	WORLD.storage_mut::<FragmentMarker>().add(entity, FragmentMarker);
+	Fragment(entity)
+}
+
+/// An entity backed by the `web_sys::ShadowRoot` of its parent, created with `AsElement::attach_shadow`
+///
+/// Its children are rendered instead of the host's own children and styles of the document don't apply to them,
+/// so classes used inside are inserted into the shadow root as well.
+/// On the server it's rendered as declarative shadow DOM, a `<template shadowrootmode>` with every style of the page.
+/// Same as fragments, it has no attributes, classes or styles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AsEntity)]
+pub struct ShadowRoot(pub Entity);
+
+impl AsElement for ShadowRoot { }
+
+fn shadow_root_name(entity: Entity) -> String { format!("shadow-root-{}", entity.0) }
+
+pub(crate) fn shadow_root(host: Entity, mode: web_sys::ShadowRootMode) -> ShadowRoot {
+	let entity = WORLD.new_entity();
+	if crate::ssr::ENABLED {
+		WORLD.storage_mut::<FragmentMarker>().add(entity, FragmentMarker);
+		WORLD.storage_mut::<crate::ssr::SsrShadowRoot>().add(entity, crate::ssr::SsrShadowRoot(mode));
+		return ShadowRoot(entity);
+	}
+
+	let root = host.get_cmp::<web_sys::Element>().attach_shadow(&web_sys::ShadowRootInit::new(mode)).expect("can't attach shadow root");
+	entity.link_node(root.as_ref());
+	WORLD.storage_mut::<web_sys::Node>().add(entity, (root.as_ref() as &web_sys::Node).clone());
+	WORLD.storage_mut::<web_sys::EventTarget>().add(entity, (root.as_ref() as &web_sys::EventTarget).clone());
+	crate::register_shadow_root(&root, &shadow_root_name(entity));
+	WORLD.storage_mut::<web_sys::ShadowRoot>().add(entity, root);
+	ShadowRoot(entity)
+}
+
+pub fn html_element<T: AsRef<web_sys::HtmlElement> + JsCast + 'static + Clone>(element: &T) -> Entity {
 	let entity = WORLD.new_entity();
 
-	let html_element = element.as_ref().clone();
+	let html_element = AsRef::<web_sys::HtmlElement>::as_ref(element).clone();
 	#[cfg(debug_assertions)] html_element.set_attribute(wasm_bindgen::intern("data-entity"), &format!("{}", entity.0)).unwrap();
 	dom_element(&WORLD, entity, &html_element);
 	WORLD.storage_mut::<web_sys::HtmlElement>().add(entity, html_element);
@@ -55,14 +134,15 @@ pub fn html_element<T: AsRef<web_sys::HtmlElement> + 'static + Clone>(element: &
 		WORLD.storage_mut::<T>().add(entity, element.clone());
 		WORLD.storage_mut::<DomTypes>().add(entity, DomTypes(hset![TypeId::of::<web_sys::HtmlElement>(), TypeId::of::<T>()]));
 	}
+	crate::hydrate::html_element::<T>(entity);
 
 	entity
 }
 
-pub fn svg_element<T: AsRef<web_sys::SvgElement> + 'static + Clone>(element: &T) -> Entity {
+pub fn svg_element<T: AsRef<web_sys::SvgElement> + JsCast + 'static + Clone>(element: &T) -> Entity {
 	let entity = WORLD.new_entity();
 
-	let svg_element = element.as_ref().clone();
+	let svg_element = AsRef::<web_sys::SvgElement>::as_ref(element).clone();
 	#[cfg(debug_assertions)] svg_element.set_attribute(wasm_bindgen::intern("data-entity"), &format!("{}", entity.0)).unwrap();
 	dom_element(&WORLD, entity, &svg_element);
 	WORLD.storage_mut::<web_sys::SvgElement>().add(entity, svg_element);
@@ -73,6 +153,7 @@ pub fn svg_element<T: AsRef<web_sys::SvgElement> + 'static + Clone>(element: &T)
 		WORLD.storage_mut::<T>().add(entity, element.clone());
 		WORLD.storage_mut::<DomTypes>().add(entity, DomTypes(hset![TypeId::of::<web_sys::SvgElement>(), TypeId::of::<T>()]));
 	}
+	crate::hydrate::svg_element::<T>(entity);
 
 	entity
 }
@@ -89,6 +170,7 @@ macro_rules! create {
 			impl AsElement for [<$html_name:camel>] { }
 
 			pub fn $html_name() -> [<$html_name:camel>] {
+				if crate::ssr::ENABLED { return [<$html_name:camel>](crate::ssr::element(crate::web_str::$html_name())); }
 				let raw: web_sys::$html_t = wasm_bindgen::JsCast::unchecked_into(
 					web_sys::window().expect("no window")
 						.document().expect("no document")
@@ -108,6 +190,7 @@ macro_rules! create {
 			impl AsElement for [<$svg_name:camel>] { }
 
 			pub fn $svg_name() -> [<$svg_name:camel>] {
+				if crate::ssr::ENABLED { return [<$svg_name:camel>](crate::ssr::element(crate::web_str::$svg_name())); }
 				let raw: web_sys::$svg_t = wasm_bindgen::JsCast::unchecked_into(
 					web_sys::window().expect("no window")
 						.document().expect("no document")
@@ -124,20 +207,20 @@ macro_rules! create {
 		pub mod strings {
 			$(
 				pub fn $html_name() -> &'static str {
-					#[cfg(debug_assertions)]
+					#[cfg(any(debug_assertions, not(target_arch = "wasm32")))]
 					{stringify!($html_name)}
 
-					#[cfg(not(debug_assertions))]
+					#[cfg(all(not(debug_assertions), target_arch = "wasm32"))]
 					{wasm_bindgen::intern(stringify!($html_name))}
 				}
 			)*
 
 			$(
 				pub fn $svg_name() -> &'static str {
-					#[cfg(debug_assertions)]
+					#[cfg(any(debug_assertions, not(target_arch = "wasm32")))]
 					{stringify!($svg_name)}
 
-					#[cfg(not(debug_assertions))]
+					#[cfg(all(not(debug_assertions), target_arch = "wasm32"))]
 					{wasm_bindgen::intern(stringify!($svg_name))}
 				}
 			)*
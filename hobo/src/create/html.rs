@@ -1,7 +1,18 @@
 use std::borrow::Cow;
-use futures_signals::signal::Signal;
+use futures_signals::signal::{Mutable, MutableSignalCloned, Signal};
+use crate::element::{SignalHandlesCollection, Tracked};
 use super::*;
 
+// typed `set_prop` with the usual builder and signal variants
+macro_rules! props {
+	($vis:vis $($name:ident, $prop:literal, $t:ty;)*) => {paste::item! {$(
+		#[inline] $vis fn [<set_ $name>](&self, x: $t) { self.set_prop($prop, x); }
+		#[must_use] #[inline] $vis fn $name(self, x: $t) -> Self { self.[<set_ $name>](x); self }
+		$vis fn [<set_ $name _signal>]<S: Signal<Item = $t> + 'static>(&self, signal: S) { self.set_prop_signal($prop, signal); }
+		#[must_use] $vis fn [<$name _signal>]<S: Signal<Item = $t> + 'static>(self, signal: S) -> Self { self.[<set_ $name _signal>](signal); self }
+	)*}};
+}
+
 pub trait BasicAttrs: AsElement {
 	#[inline] fn set_name<'a>(&self, x: impl Into<Cow<'a, str>>) { self.set_attr(web_str::name(), x); }
 	#[must_use] #[inline] fn name<'a>(self, x: impl Into<Cow<'a, str>>) -> Self where Self: Sized { self.set_name(x); self }
@@ -65,24 +76,107 @@ impl Input {
 	#[inline] pub fn set_type_url(&self) { self.set_attr(web_str::r#type(), web_str::url()); }
 
 	#[inline] pub fn get_checked(&self) -> bool { self.get_cmp::<web_sys::HtmlInputElement>().checked() }
+
+	// properties are skipped on the server, so `checked` is rendered as the attribute there instead
+	#[inline] pub fn set_checked(&self, x: bool) { if crate::ssr::ENABLED { self.set_bool_attr(web_str::checked(), x); } else { self.set_prop("checked", x); } }
+	#[must_use] #[inline] pub fn checked(self, x: bool) -> Self { self.set_checked(x); self }
+	pub fn set_checked_signal<S: Signal<Item = bool> + 'static>(&self, signal: S) {
+		if crate::ssr::ENABLED { self.set_bool_attr_signal(web_str::checked(), signal); } else { self.set_prop_signal("checked", signal); }
+	}
+	#[must_use] pub fn checked_signal<S: Signal<Item = bool> + 'static>(self, signal: S) -> Self { self.set_checked_signal(signal); self }
+
+	props! { pub
+		indeterminate, "indeterminate", bool;
+	}
+
+	/// keep `checked` of a checkbox and the `Mutable` in sync both ways, the `Mutable` wins initially
+	pub fn add_checked_binding(&self, checked: Mutable<bool>) {
+		let this = *self;
+		if this.is_dead() { log::warn!("add_checked_binding dead {:?}", this.as_entity()); return; }
+		let handle = checked.signal().subscribe(move |x| {
+			if crate::ssr::ENABLED { this.set_bool_attr(web_str::checked(), x); return; }
+			let element = this.get_cmp::<web_sys::HtmlInputElement>();
+			if element.checked() != x { element.set_checked(x); }
+		});
+		this.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+		this.add_on_change(move |_| checked.set_neq(this.get_checked()));
+	}
+	#[must_use] pub fn bind_checked(self, checked: Mutable<bool>) -> Self { self.add_checked_binding(checked); self }
+
+	/// keep the value of a number or range input and the `Mutable` in sync both ways, the `Mutable` wins initially
+	///
+	/// while the input doesn't hold a valid number the `Mutable` keeps its last value
+	pub fn add_number_binding(&self, number: Mutable<f64>) {
+		let this = *self;
+		if this.is_dead() { log::warn!("add_number_binding dead {:?}", this.as_entity()); return; }
+		let handle = number.signal().subscribe(move |x| {
+			if crate::ssr::ENABLED { this.set_attr(web_str::value(), x.to_string()); return; }
+			let element = this.get_cmp::<web_sys::HtmlInputElement>();
+			#[allow(clippy::float_cmp, reason = "only skips redundant updates, so typing e.g. `1.` isn't reset to `1`")]
+			if element.value_as_number() != x { element.set_value_as_number(x); }
+		});
+		this.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+		this.add_on_input(move |_| {
+			let x = this.get_cmp::<web_sys::HtmlInputElement>().value_as_number();
+			if !x.is_nan() { number.set_neq(x); }
+		});
+	}
+	#[must_use] pub fn bind_number(self, number: Mutable<f64>) -> Self { self.add_number_binding(number); self }
 }
 
+struct Value;
+
 pub trait StringValue: AsElement {
 	#[must_use]
 	fn value_attr<'a>(self, value: impl Into<Cow<'a, str>>) -> Self where Self: Sized { self.set_value_attr(value); self }
 	fn set_value_attr<'a>(&self, value: impl Into<Cow<'a, str>>) { self.set_attr(web_str::value(), value) }
 	fn value(&self) -> String;
 	fn set_value(&self, x: &str);
+
+	/// the current value, updated on every `input` event
+	///
+	/// the `Mutable` and its listener are created on first call, shared by all callers and removed with the element
+	fn value_signal(&self) -> MutableSignalCloned<String> where Self: Copy + 'static {
+		let this = *self;
+		Tracked::<Value, String>::get_or_init(self.as_entity(), || this.value(), |target, value| vec![
+			target.on_input({ let value = value.clone(); move |_| value.set_neq(this.value()) }),
+		]).signal_cloned()
+	}
+
+	/// keep the value and the `Mutable` in sync both ways, the `Mutable` wins initially
+	fn add_value_binding(&self, value: Mutable<String>) where Self: Copy + 'static {
+		let this = *self;
+		if this.is_dead() { log::warn!("add_value_binding dead {:?}", this.as_entity()); return; }
+		// comparing first so that typing doesn't get the value reset, which would move the caret
+		let handle = value.signal_cloned().subscribe(move |x| if this.value() != x { this.set_value(&x); });
+		this.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+		this.add_on_input(move |_| value.set_neq(this.value()));
+	}
+	#[must_use]
+	fn bind_value(self, value: Mutable<String>) -> Self where Self: Copy + 'static { self.add_value_binding(value); self }
 }
 
+// on the server the value is rendered as the initial value
 impl StringValue for Input {
-	#[inline] fn value(&self) -> String { self.get_cmp::<web_sys::HtmlInputElement>().value() }
-	#[inline] fn set_value(&self, x: &str) { self.get_cmp::<web_sys::HtmlInputElement>().set_value(x) }
+	#[inline] fn value(&self) -> String {
+		if crate::ssr::ENABLED { return self.get_attr(web_str::value()).unwrap_or_default(); }
+		self.get_cmp::<web_sys::HtmlInputElement>().value()
+	}
+	#[inline] fn set_value(&self, x: &str) {
+		if crate::ssr::ENABLED { self.set_attr(web_str::value(), x); return; }
+		self.get_cmp::<web_sys::HtmlInputElement>().set_value(x);
+	}
 }
 
 impl StringValue for Textarea {
-	#[inline] fn value(&self) -> String { self.get_cmp::<web_sys::HtmlTextAreaElement>().value() }
-	#[inline] fn set_value(&self, x: &str) { self.get_cmp::<web_sys::HtmlTextAreaElement>().set_value(x) }
+	#[inline] fn value(&self) -> String {
+		if crate::ssr::ENABLED { return crate::ssr::text(self.as_entity()).unwrap_or_default(); }
+		self.get_cmp::<web_sys::HtmlTextAreaElement>().value()
+	}
+	#[inline] fn set_value(&self, x: &str) {
+		if crate::ssr::ENABLED { self.set_text(x); return; }
+		self.get_cmp::<web_sys::HtmlTextAreaElement>().set_value(x);
+	}
 }
 
 pub trait Placeholder: AsElement {
@@ -100,8 +194,58 @@ impl Placeholder for Textarea {
 
 impl Select {
 	#[inline] pub fn selected_index(&self) -> i32 { self.get_cmp::<web_sys::HtmlSelectElement>().selected_index() }
+	#[inline] pub fn set_selected_index(&self, x: i32) { self.set_prop("selectedIndex", x); }
+	pub fn set_selected_index_signal<S: Signal<Item = i32> + 'static>(&self, signal: S) { self.set_prop_signal("selectedIndex", signal); }
+
+	/// keep the value of the selected option and the `Mutable` in sync both ways, the `Mutable` wins initially
+	///
+	/// `None` means nothing is selected
+	pub fn add_selected_binding(&self, selected: Mutable<std::option::Option<String>>) {
+		let this = *self;
+		if this.is_dead() { log::warn!("add_selected_binding dead {:?}", this.as_entity()); return; }
+		// the selection lives in `option` children, which aren't known here
+		if crate::ssr::ENABLED { return; }
+		let handle = selected.signal_cloned().subscribe(move |x| {
+			let element = this.get_cmp::<web_sys::HtmlSelectElement>();
+			match x {
+				Some(x) => if element.value() != x { element.set_value(&x); },
+				None => element.set_selected_index(-1),
+			}
+		});
+		this.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+		this.add_on_change(move |_| {
+			let element = this.get_cmp::<web_sys::HtmlSelectElement>();
+			selected.set_neq((element.selected_index() >= 0).then(|| element.value()));
+		});
+	}
+	#[must_use] pub fn bind_selected(self, selected: Mutable<std::option::Option<String>>) -> Self { self.add_selected_binding(selected); self }
+}
+
+/// Properties of `Video` and `Audio`
+pub trait Media: AsElement {
+	props! {
+		muted, "muted", bool;
+		volume, "volume", f64;
+		playback_rate, "playbackRate", f64;
+		current_time, "currentTime", f64;
+	}
+
+	/// start playing, autoplay restrictions fail silently
+	fn play(&self) {
+		if crate::ssr::ENABLED { return; }
+		let Ok(promise) = self.get_cmp::<web_sys::Element>().unchecked_ref::<web_sys::HtmlMediaElement>().play() else { return; };
+		// awaiting the promise handles its rejection, otherwise the browser reports it as uncaught
+		wasm_bindgen_futures::spawn_local(async move { wasm_bindgen_futures::JsFuture::from(promise).await.ok(); });
+	}
+	fn pause(&self) {
+		if crate::ssr::ENABLED { return; }
+		self.get_cmp::<web_sys::Element>().unchecked_ref::<web_sys::HtmlMediaElement>().pause().expect("can't pause");
+	}
 }
 
+impl Media for Video {}
+impl Media for Audio {}
+
 impl Img {
 	#[must_use]
 	#[inline] pub fn src<'a>(self, url: impl Into<Cow<'a, str>>) -> Self { self.attr(web_str::src(), url) }
@@ -135,10 +279,77 @@ impl Label {
 
 impl Form {
 	#[inline] pub fn radio_value<'a>(&self, x: impl Into<Cow<'a, str>>) -> std::option::Option<String> {
+		self.radio_node_list(&x.into()).map(|x| x.value())
+	}
+
+	fn radio_node_list(self, name: &str) -> std::option::Option<web_sys::RadioNodeList> {
 		self.get_cmp::<web_sys::HtmlFormElement>()
 			.elements()
-			.get_with_name(&x.into())
+			.get_with_name(name)
 			.and_then(|x| x.dyn_into::<web_sys::RadioNodeList>().ok())
-			.map(|x| x.value())
 	}
+
+	/// keep the value of the checked radio button named `name` and the `Mutable` in sync both ways, the `Mutable` wins initially
+	///
+	/// `None` means no radio button is checked
+	pub fn add_radio_binding(&self, name: impl Into<String>, value: Mutable<std::option::Option<String>>) {
+		let this = *self;
+		if this.is_dead() { log::warn!("add_radio_binding dead {:?}", this.as_entity()); return; }
+		if crate::ssr::ENABLED { return; }
+		let name = name.into();
+		let handle = value.signal_cloned().subscribe({
+			let name = name.clone();
+			move |x| {
+				let Some(radios) = this.radio_node_list(&name) else { return; };
+				match x {
+					Some(x) => if radios.value() != x { radios.set_value(&x); },
+					None => for i in 0..radios.length() {
+						if let Some(radio) = radios.get(i).and_then(|x| x.dyn_into::<web_sys::HtmlInputElement>().ok()) { radio.set_checked(false); }
+					},
+				}
+			}
+		});
+		this.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+		// `change` bubbles up from the radio buttons
+		this.add_on_change(move |_| value.set_neq(this.radio_value(&name).filter(|x| !x.is_empty())));
+	}
+	#[must_use] pub fn bind_radio(self, name: impl Into<String>, value: Mutable<std::option::Option<String>>) -> Self { self.add_radio_binding(name, value); self }
+}
+
+#[test]
+fn checked_on_the_server() {
+	use crate::create as e;
+	use futures_signals::signal::Mutable;
+
+	let checked = Mutable::new(true);
+	let root = e::div()
+		.child(e::input().type_checkbox().checked(true).indeterminate(true))
+		.child(e::input().type_checkbox().checked_signal(checked.signal()))
+		.child(e::video().muted(true));
+	assert_eq!(crate::ssr::render_html(root), r#"<div><input type="checkbox" checked=""><input type="checkbox" checked=""><video></video></div>"#);
+	checked.set(false);
+	assert_eq!(crate::ssr::render_html(root), r#"<div><input type="checkbox" checked=""><input type="checkbox"><video></video></div>"#);
+}
+
+#[test]
+fn bindings_on_the_server() {
+	use crate::create as e;
+	use futures_signals::signal::Mutable;
+
+	let checked = Mutable::new(true);
+	let number = Mutable::new(1.5);
+	let value = Mutable::new("a".to_owned());
+	let root = e::div()
+		.child(e::input().type_checkbox().bind_checked(checked.clone()))
+		.child(e::input().bind_number(number.clone()))
+		.child(e::input().bind_value(value.clone()))
+		.child(e::textarea().bind_value(value.clone()));
+	crate::ssr::run_pending();
+	assert_eq!(crate::ssr::render_html(root), r#"<div><input type="checkbox" checked=""><input value="1.5"><input value="a"><textarea>a</textarea></div>"#);
+
+	checked.set(false);
+	number.set(2.);
+	value.set("b".to_owned());
+	crate::ssr::run_pending();
+	assert_eq!(crate::ssr::render_html(root), r#"<div><input type="checkbox"><input value="2"><input value="b"><textarea>b</textarea></div>"#);
 }
@@ -0,0 +1,130 @@
+//! web components backed by hobo elements
+//!
+//! A custom element builds its content when it's inserted into the document and removes it when it's taken out,
+//! so it can be used from plain HTML or any other framework:
+//!
+//! ```ignore
+//! #[derive(AsElement, Clone, Copy)]
+//! struct Rating(e::Div);
+//!
+//! impl CustomElement for Rating {
+//!     const OBSERVED_ATTRIBUTES: &'static [&'static str] = &["stars"];
+//! }
+//!
+//! struct Rated(u8);
+//!
+//! impl HostEvent for Rated {
+//!     const NAME: &'static str = "rated";
+//!     fn detail(&self) -> JsValue { self.0.into() }
+//! }
+//!
+//! hobo::define_custom_element("x-rating", |host| Rating(e::div()
+//!     .text_signal(host.attribute_signal("stars").map(|x| x.unwrap_or_default()))
+//!     .on_click(move |_| host.fire(&Rated(5)))
+//! ));
+//! ```
+
+use crate::prelude::*;
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use std::{cell::RefCell, collections::{HashMap, HashSet}};
+
+thread_local! {
+	// hosts whose entities are being removed, their nodes belong to whoever inserted them
+	static DISCONNECTING: RefCell<HashSet<Entity>> = RefCell::default();
+}
+
+// whether removing the entity should leave its node where it is
+pub(crate) fn keeps_node(entity: Entity) -> bool { DISCONNECTING.with_borrow(|x| x.contains(&entity)) }
+
+#[wasm_bindgen(inline_js = "
+export function define_custom_element(name, observed, connected, disconnected, changed) {
+	customElements.define(name, class extends HTMLElement {
+		static get observedAttributes() { return observed; }
+		connectedCallback() { connected(this); }
+		disconnectedCallback() { disconnected(this); }
+		attributeChangedCallback(name, _, value) { changed(this, name, value); }
+	});
+}
+")]
+extern "C" {
+	#[wasm_bindgen(js_name = define_custom_element)]
+	fn define_class(name: &str, observed: &js_sys::Array, connected: &JsValue, disconnected: &JsValue, changed: &JsValue);
+}
+
+/// The element that a custom element's content is built with
+pub trait CustomElement: AsElement + 'static {
+	/// Attributes that `Host::attribute` keeps up to date
+	const OBSERVED_ATTRIBUTES: &'static [&'static str] = &[];
+}
+
+/// A payload of a `CustomEvent` dispatched by `Host::fire`
+pub trait HostEvent {
+	/// The type of the event, i.e. what listeners pass to `addEventListener`
+	const NAME: &'static str;
+	/// `event.detail`
+	fn detail(&self) -> JsValue { JsValue::UNDEFINED }
+}
+
+/// The custom element itself, the content built for it is its only child
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AsElement)]
+pub struct Host(pub Entity);
+
+struct HostAttributes(HashMap<&'static str, Mutable<Option<String>>>);
+
+impl Host {
+	/// The value of an observed attribute, `None` if it's missing
+	pub fn attribute(&self, name: &str) -> Mutable<Option<String>> {
+		if self.is_dead() { log::warn!("attribute dead {:?}", self.as_entity()); return Mutable::default(); }
+		if let Some(x) = self.get_cmp::<HostAttributes>().0.get(name) { return x.clone(); }
+		log::warn!("{name} isn't an observed attribute, so it won't be updated");
+		Mutable::new(self.get_attr(name))
+	}
+
+	pub fn attribute_signal(&self, name: &str) -> MutableSignalCloned<Option<String>> { self.attribute(name).signal_cloned() }
+
+	/// Dispatch a `CustomEvent` on the custom element, it bubbles and crosses shadow DOM boundaries
+	pub fn fire<E: HostEvent>(&self, e: &E) {
+		let event = web_sys::CustomEvent::new_with_event_init_dict(E::NAME, web_sys::CustomEventInit::new().bubbles(true).composed(true).detail(&e.detail()))
+			.expect("can't create custom event");
+		self.get_cmp::<web_sys::EventTarget>().dispatch_event(&event).expect("can't dispatch event");
+	}
+}
+
+fn connected<T: CustomElement>(element: &web_sys::HtmlElement, f: &impl Fn(Host) -> T) {
+	if Entity::from_node(element).is_some() { return; }
+
+	let host = Host(crate::create::html_element(element)).allow_no_parent();
+	#[cfg(debug_assertions)] element.remove_attribute("data-entity").expect("can't remove attribute");
+	host.add_component(HostAttributes(T::OBSERVED_ATTRIBUTES.iter().map(|&name| (name, Mutable::new(element.get_attribute(name)))).collect()));
+	host.add_child(f(host));
+}
+
+fn disconnected(element: &web_sys::HtmlElement) {
+	let Some(entity) = Entity::from_node(element) else { return; };
+
+	// the custom element itself could already be somewhere else, e.g. if it was moved, so only the content is removed
+	DISCONNECTING.with_borrow_mut(|x| x.insert(entity));
+	entity.remove();
+	DISCONNECTING.with_borrow_mut(|x| x.remove(&entity));
+}
+
+// initial values are read on connect, so changes before that are ignored
+fn attribute_changed(element: &web_sys::HtmlElement, name: &str, value: Option<String>) {
+	let Some(entity) = Entity::from_node(element) else { return; };
+	if let Some(x) = entity.get_cmp::<HostAttributes>().0.get(name) { x.set_neq(value); }
+}
+
+/// Register a custom element named `name`, `f` builds the content of every instance when it's inserted into the document
+///
+/// The name has to contain a `-`. Does nothing on the server.
+pub fn define_custom_element<T: CustomElement>(name: &str, f: impl Fn(Host) -> T + 'static) {
+	if crate::ssr::ENABLED { return; }
+
+	let observed = T::OBSERVED_ATTRIBUTES.iter().map(|&x| JsValue::from_str(x)).collect::<js_sys::Array>();
+	let connected = Closure::<dyn Fn(web_sys::HtmlElement)>::new(move |x: web_sys::HtmlElement| connected(&x, &f));
+	let disconnected = Closure::<dyn Fn(web_sys::HtmlElement)>::new(|x: web_sys::HtmlElement| disconnected(&x));
+	let changed = Closure::<dyn Fn(web_sys::HtmlElement, String, Option<String>)>::new(|x: web_sys::HtmlElement, name: String, value: Option<String>| attribute_changed(&x, &name, value));
+
+	// custom elements can't be undefined, so the callbacks live forever
+	define_class(name, &observed, &connected.into_js_value(), &disconnected.into_js_value(), &changed.into_js_value());
+}
@@ -1,47 +1,166 @@
 //! everything that has to do with HTML event handling
 
+mod delegation;
+
 use crate::{prelude::*, AsEntity, AsElement};
+pub use delegation::set_delegation;
+use std::sync::atomic::{AtomicU64, Ordering};
+use wasm_bindgen::convert::FromWasmAbi;
+
+/// Options for `addEventListener`, the default is to not set any of them
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct EventOptions {
+	/// the handler is called during the capture phase rather than the bubble phase
+	pub capture: bool,
+	/// the handler is removed after it's called once
+	pub once: bool,
+	/// the handler promises not to call `prevent_default`, so e.g. scrolling doesn't have to wait for it
+	pub passive: bool,
+}
+
+impl EventOptions {
+	#[must_use] pub const fn capture(self) -> Self { Self { capture: true, ..self } }
+	#[must_use] pub const fn once(self) -> Self { Self { once: true, ..self } }
+	#[must_use] pub const fn passive(self) -> Self { Self { passive: true, ..self } }
+}
+
+static NEXT_HANDLER_ID: AtomicU64 = AtomicU64::new(0);
 
 /// A dom event subscription, that will unsubscribe from dom when dropped
 pub struct EventHandler {
+	id: u64,
 	target: web_sys::EventTarget,
 	name: &'static str,
+	options: EventOptions,
 	cb: Box<dyn std::convert::AsRef<JsValue>>,
 }
 
-impl Drop for EventHandler {
-	fn drop(&mut self) {
-		let res = self.target.remove_event_listener_with_callback(self.name, (*self.cb).as_ref().unchecked_ref());
+impl EventHandler {
+	fn new(id: u64, target: &web_sys::EventTarget, name: &'static str, options: EventOptions, cb: Box<dyn std::convert::AsRef<JsValue>>) -> Self {
+		let handler = EventHandler { id, target: target.clone(), name, options, cb };
+		handler.listen();
+		handler
+	}
+
+	fn listen(&self) {
+		let mut raw_options = web_sys::AddEventListenerOptions::new();
+		raw_options.capture(self.options.capture).once(self.options.once).passive(self.options.passive);
+		self.target.add_event_listener_with_callback_and_add_event_listener_options(self.name, (*self.cb).as_ref().unchecked_ref(), &raw_options).expect("can't add event listener");
+	}
+
+	fn unlisten(&self) {
+		let res = self.target.remove_event_listener_with_callback_and_bool(self.name, (*self.cb).as_ref().unchecked_ref(), self.options.capture);
 		if let Err(_e) = res {
 			// TODO?
 			// log::warn!("remove_event_listener_with_callback failed with error: {}", serde_json::to_string_pretty(&e.into_serde::<serde_json::Value>().unwrap()).unwrap());
 		}
 	}
+
+	// move the listener to another target, e.g. when a node is replaced by its server-rendered twin
+	pub(crate) fn retarget(&mut self, target: &web_sys::EventTarget) {
+		self.unlisten();
+		self.target = target.clone();
+		self.listen();
+	}
+}
+
+impl Drop for EventHandler {
+	fn drop(&mut self) { self.unlisten(); }
+}
+
+// whether the entity itself listens to `name`, delegated or not
+pub(crate) fn has_handler(entity: Entity, name: &str) -> bool {
+	entity.try_get_cmp::<Vec<EventHandler>>().is_some_and(|x| x.iter().any(|x| x.name == name)) || delegation::has(entity, name)
+}
+
+// for handlers with `once`, the browser has already removed the listener, this drops the handler itself
+// wasm-bindgen defers freeing the closure until it returns
+fn drop_handler(entity: Entity, id: u64) {
+	if let Some(mut handlers) = entity.try_get_cmp_mut::<Vec<EventHandler>>() {
+		handlers.retain(|x| x.id != id);
+	}
+}
+
+#[extend::ext(name = RawDomEventsGeneric)]
+pub impl web_sys::EventTarget {
+	/// subscribe to an arbitrary event by name
+	fn on_event_with<E: FromWasmAbi + 'static>(&self, name: &'static str, options: EventOptions, f: impl FnMut(E) + 'static) -> EventHandler {
+		let handler = Closure::wrap(Box::new(f) as Box<dyn FnMut(E) + 'static>);
+		EventHandler::new(NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed), self, name, options, Box::new(handler))
+	}
+
+	/// subscribe to an arbitrary event by name, the handler is only called once and can take ownership of captured values
+	fn on_event_once<E: FromWasmAbi + 'static>(&self, name: &'static str, f: impl FnOnce(E) + 'static) -> EventHandler {
+		let handler = Closure::once(f);
+		EventHandler::new(NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed), self, name, EventOptions::default().once(), Box::new(handler))
+	}
+}
+
+pub trait DomEventsGeneric: AsElement {
+	/// handlers added to this element afterwards are delegated to a document-wide listener (or not), regardless of `set_delegation`
+	///
+	/// handlers with any `EventOptions` set are never delegated
+	fn set_delegate_events(&self, delegate: bool) { self.add_component(delegation::DelegateEvents(delegate)); }
+	#[must_use]
+	fn delegate_events(self, delegate: bool) -> Self where Self: Sized { self.set_delegate_events(delegate); self }
+
+	fn add_on_event_with<E: FromWasmAbi + JsCast + 'static>(&self, name: &'static str, options: EventOptions, mut f: impl FnMut(E) + 'static) {
+		let entity = self.as_entity();
+		if entity.is_dead() { log::warn!("callback handler entity dead {:?}", entity); return; }
+		// nothing to listen to without a DOM
+		if crate::ssr::ENABLED { return; }
+		if options == EventOptions::default() && delegation::is_delegated(entity, name) {
+			delegation::add(entity, name, false, move |e| f(e.clone().unchecked_into()));
+			return;
+		}
+		let target = entity.get_cmp::<web_sys::EventTarget>();
+		let handler = if options.once {
+			let id = NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed);
+			let handler = Closure::wrap(Box::new(move |e: E| { f(e); drop_handler(entity, id); }) as Box<dyn FnMut(E) + 'static>);
+			EventHandler::new(id, &target, name, options, Box::new(handler))
+		} else {
+			target.on_event_with(name, options, f)
+		};
+		entity.get_cmp_mut_or_default::<Vec<EventHandler>>().push(handler);
+	}
+
+	#[must_use]
+	fn on_event_with<E: FromWasmAbi + JsCast + 'static>(self, name: &'static str, options: EventOptions, f: impl FnMut(E) + 'static) -> Self where Self: Sized { self.add_on_event_with(name, options, f); self }
+
+	fn add_on_event_once<E: FromWasmAbi + JsCast + 'static>(&self, name: &'static str, f: impl FnOnce(E) + 'static) {
+		let entity = self.as_entity();
+		if entity.is_dead() { log::warn!("callback handler entity dead {:?}", entity); return; }
+		if crate::ssr::ENABLED { return; }
+		if delegation::is_delegated(entity, name) {
+			let mut f = Some(f);
+			delegation::add(entity, name, true, move |e| if let Some(f) = f.take() { f(e.clone().unchecked_into()); });
+			return;
+		}
+		let id = NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed);
+		let handler = Closure::once(move |e: E| { f(e); drop_handler(entity, id); });
+		let target = entity.get_cmp::<web_sys::EventTarget>();
+		let handler = EventHandler::new(id, &target, name, EventOptions::default().once(), Box::new(handler));
+		entity.get_cmp_mut_or_default::<Vec<EventHandler>>().push(handler);
+	}
+
+	#[must_use]
+	fn on_event_once<E: FromWasmAbi + JsCast + 'static>(self, name: &'static str, f: impl FnOnce(E) + 'static) -> Self where Self: Sized { self.add_on_event_once(name, f); self }
 }
 
+impl<T: AsElement> DomEventsGeneric for T {}
+
 macro_rules! generate_events {
 	($($event_kind:ident, $name:ident, $f:ident);+$(;)*) => {paste::item!{
 
 		#[extend::ext(name = RawDomEvents)]
 		pub impl web_sys::EventTarget {$(
-			fn $f(&self, f: impl FnMut(web_sys::$event_kind) + 'static) -> EventHandler {
-				let handler = Closure::wrap(Box::new(f) as Box<dyn FnMut(web_sys::$event_kind) + 'static>);
-				self.add_event_listener_with_callback(web_str::$name(), handler.as_ref().unchecked_ref()).expect("can't add event listener");
-				EventHandler {
-					target: self.clone(),
-					name: web_str::$name(),
-					cb: Box::new(handler),
-				}
-			}
+			fn $f(&self, f: impl FnMut(web_sys::$event_kind) + 'static) -> EventHandler { self.on_event_with(web_str::$name(), EventOptions::default(), f) }
+			fn [<$f _with>](&self, options: EventOptions, f: impl FnMut(web_sys::$event_kind) + 'static) -> EventHandler { self.on_event_with(web_str::$name(), options, f) }
+			fn [<$f _once>](&self, f: impl FnOnce(web_sys::$event_kind) + 'static) -> EventHandler { self.on_event_once(web_str::$name(), f) }
 		)+}
 
 		pub trait DomEvents: AsElement {$(
-			fn [<add_ $f>](&self, f: impl FnMut(web_sys::$event_kind) + 'static) {
-				let entity = self.as_entity();
-				if entity.is_dead() { log::warn!("callback handler entity dead {:?}", entity); return; }
-				let target = entity.get_cmp::<web_sys::EventTarget>();
-				entity.get_cmp_mut_or_default::<Vec<EventHandler>>().push(target.$f(f));
-			}
+			fn [<add_ $f>](&self, f: impl FnMut(web_sys::$event_kind) + 'static) { self.add_on_event_with(web_str::$name(), EventOptions::default(), f); }
 
 			#[must_use]
 			fn $f(self, f: impl FnMut(web_sys::$event_kind) + 'static) -> Self where Self: Sized { self.[<add_ $f>](f); self }
@@ -55,6 +174,16 @@ macro_rules! generate_events {
 				let f = f(&self);
 				self.$f(f)
 			}
+
+			fn [<add_ $f _with>](&self, options: EventOptions, f: impl FnMut(web_sys::$event_kind) + 'static) { self.add_on_event_with(web_str::$name(), options, f); }
+
+			#[must_use]
+			fn [<$f _with>](self, options: EventOptions, f: impl FnMut(web_sys::$event_kind) + 'static) -> Self where Self: Sized { self.[<add_ $f _with>](options, f); self }
+
+			fn [<add_ $f _once>](&self, f: impl FnOnce(web_sys::$event_kind) + 'static) { self.add_on_event_once(web_str::$name(), f); }
+
+			#[must_use]
+			fn [<$f _once>](self, f: impl FnOnce(web_sys::$event_kind) + 'static) -> Self where Self: Sized { self.[<add_ $f _once>](f); self }
 		)+}
 
 		impl<T: AsElement> DomEvents for T {}
@@ -74,25 +203,57 @@ generate_events! {
 	MouseEvent,      mouseup,            on_mouse_up;
 	KeyboardEvent,   keydown,            on_key_down;
 	KeyboardEvent,   keyup,              on_key_up;
+	KeyboardEvent,   keypress,           on_key_press;
 	Event,           change,             on_change;
 	Event,           scroll,             on_scroll;
 	UiEvent,         resize,             on_resize;
 	FocusEvent,      blur,               on_blur;
 	FocusEvent,      focus,              on_focus;
+	FocusEvent,      focusin,            on_focus_in;
+	FocusEvent,      focusout,           on_focus_out;
 	TouchEvent,      touchstart,         on_touch_start;
 	TouchEvent,      touchend,           on_touch_end;
 	TouchEvent,      touchmove,          on_touch_move;
 	TouchEvent,      touchcancel,        on_touch_cancel;
+	PointerEvent,    pointerdown,        on_pointer_down;
+	PointerEvent,    pointermove,        on_pointer_move;
+	PointerEvent,    pointerup,          on_pointer_up;
+	PointerEvent,    pointercancel,      on_pointer_cancel;
+	PointerEvent,    pointerenter,       on_pointer_enter;
+	PointerEvent,    pointerleave,       on_pointer_leave;
+	PointerEvent,    pointerover,        on_pointer_over;
+	PointerEvent,    pointerout,         on_pointer_out;
+	PointerEvent,    gotpointercapture,  on_got_pointer_capture;
+	PointerEvent,    lostpointercapture, on_lost_pointer_capture;
 	WheelEvent,      wheel,              on_wheel;
 	Event,           load,               on_load;
 	Event,           canplay,            on_can_play;
 	DragEvent,       drag,               on_drag;
+	DragEvent,       dragstart,          on_drag_start;
+	DragEvent,       dragend,            on_drag_end;
+	DragEvent,       dragenter,          on_drag_enter;
+	DragEvent,       dragleave,          on_drag_leave;
+	DragEvent,       dragover,           on_drag_over;
+	DragEvent,       drop,               on_drop;
 	SubmitEvent,     submit,             on_submit;
 	InputEvent,      input,              on_input;
+	InputEvent,      beforeinput,        on_before_input;
+	CompositionEvent, compositionstart,  on_composition_start;
+	CompositionEvent, compositionupdate, on_composition_update;
+	CompositionEvent, compositionend,    on_composition_end;
 	AnimationEvent,  animationcancel,    on_animation_cancel;
 	AnimationEvent,  animationend,       on_animation_end;
 	AnimationEvent,  animationiteration, on_animation_iteration;
 	AnimationEvent,  animationstart,     on_animation_start;
+	TransitionEvent, transitionstart,    on_transition_start;
+	TransitionEvent, transitionend,      on_transition_end;
+	TransitionEvent, transitioncancel,   on_transition_cancel;
+	TransitionEvent, transitionrun,      on_transition_run;
+	Event,           play,               on_play;
+	Event,           pause,              on_pause;
+	Event,           ended,              on_ended;
+	Event,           timeupdate,         on_time_update;
+	Event,           volumechange,       on_volume_change;
 	PopStateEvent,   popstate,           on_pop_state;
 	HashChangeEvent, hashchange,         on_hash_change;
 	ClipboardEvent,  cut,                on_cut;
@@ -132,7 +293,6 @@ generate_events! {
 // PageTransitionEvent
 // PaymentMethodChangeEvent
 // PaymentRequestUpdateEvent
-// PointerEvent
 // PopupBlockedEvent
 // PresentationConnectionAvailableEvent
 // PresentationConnectionCloseEvent
@@ -154,10 +314,29 @@ generate_events! {
 // TcpServerSocketEvent
 // TcpSocketErrorEvent
 // TcpSocketEvent
-// CompositionEvent
 // TimeEvent
 // TrackEvent
-// TransitionEvent
 // UserProximityEvent
 // WebGlContextEvent
 // DeviceLightEvent
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn drops_once_handlers() {
+	use crate::create as e;
+	use std::{cell::Cell, rc::Rc};
+
+	let count = Rc::new(Cell::new(0));
+	let handler_count = |x: e::Div| x.try_get_cmp::<Vec<EventHandler>>().map_or(0, |x| x.len());
+
+	let once = e::div().delegate_events(false).on_click_once({ let count = Rc::clone(&count); move |_| count.set(count.get() + 1) });
+	let with_options = e::div().delegate_events(false).on_click_with(EventOptions::default().once(), { let count = Rc::clone(&count); move |_| count.set(count.get() + 1) });
+	for element in [once, with_options] {
+		let element = element.allow_no_parent();
+		assert_eq!(handler_count(element), 1);
+		element.get_cmp::<web_sys::HtmlElement>().click();
+		assert_eq!(handler_count(element), 0);
+		element.get_cmp::<web_sys::HtmlElement>().click();
+	}
+	assert_eq!(count.get(), 2);
+}
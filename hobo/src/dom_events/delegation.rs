@@ -0,0 +1,236 @@
+//! delegated event handling
+//!
+//! instead of a DOM listener per element, there is one listener per event type on the document,
+//! which calls the handlers of the entities along the event's path, starting from the target
+//!
+//! The document listener runs after the listeners on the elements themselves, so `stop_propagation` in a delegated
+//! handler only stops delegated handlers of ancestors. `current_target` is set to each entity's node while its handlers run.
+//! Events that don't bubble never reach the document, so handlers for them are added to the element directly.
+
+use super::{EventHandler, EventOptions, RawDomEventsGeneric, NEXT_HANDLER_ID};
+use crate::prelude::*;
+use std::{cell::RefCell, collections::HashMap, rc::Rc, sync::atomic::Ordering};
+
+type DelegatedFn = Rc<RefCell<dyn FnMut(&web_sys::Event)>>;
+
+struct DelegatedHandler {
+	id: u64,
+	once: bool,
+	f: DelegatedFn,
+}
+
+/// Handlers of an entity that are called by the document-wide listeners
+#[derive(Default)]
+pub struct DelegatedHandlers(HashMap<&'static str, Vec<DelegatedHandler>>);
+
+/// Whether an element's handlers are delegated, overrides the global setting
+pub struct DelegateEvents(pub bool);
+
+#[derive(Default)]
+struct Delegation {
+	enabled: bool,
+	root_listeners: HashMap<&'static str, EventHandler>,
+}
+
+/// Turn delegated event handling on or off for all elements that don't opt in or out themselves
+///
+/// Only affects handlers added afterwards.
+pub fn set_delegation(enabled: bool) { Delegation::resource_mut_or_default().enabled = enabled; }
+
+// the document only sees these if it listens in the capture phase, which would run delegated handlers before everything else
+const NON_BUBBLING: &[&str] = &[
+	"focus", "blur", "mouseenter", "mouseleave", "pointerenter", "pointerleave", "load", "error", "abort", "scroll", "scrollend",
+	"toggle", "invalid", "cancel", "close", "loadstart", "progress", "suspend", "emptied", "stalled", "loadedmetadata", "loadeddata",
+	"canplay", "canplaythrough", "playing", "waiting", "seeking", "seeked", "ended", "durationchange", "timeupdate", "play", "pause",
+	"ratechange", "volumechange",
+];
+
+pub fn is_delegated(entity: Entity, name: &str) -> bool {
+	if NON_BUBBLING.contains(&name) { return false; }
+	entity.try_get_cmp::<DelegateEvents>().map_or_else(|| Delegation::try_resource().is_some_and(|x| x.enabled), |x| x.0)
+}
+
+pub fn has(entity: Entity, name: &str) -> bool {
+	entity.try_get_cmp::<DelegatedHandlers>().is_some_and(|x| x.0.get(name).is_some_and(|x| !x.is_empty()))
+}
+
+pub fn add(entity: Entity, name: &'static str, once: bool, f: impl FnMut(&web_sys::Event) + 'static) {
+	let has_root_listener = Delegation::resource_or_default().root_listeners.contains_key(name);
+	if !has_root_listener {
+		let document: web_sys::EventTarget = web_sys::window().expect("no window").document().expect("no document").into();
+		let listener = document.on_event_with(name, EventOptions::default(), move |e: web_sys::Event| dispatch(name, &e));
+		Delegation::resource_mut().root_listeners.insert(name, listener);
+	}
+
+	let id = NEXT_HANDLER_ID.fetch_add(1, Ordering::Relaxed);
+	entity.get_cmp_mut_or_default::<DelegatedHandlers>().0.entry(name).or_default().push(DelegatedHandler { id, once, f: Rc::new(RefCell::new(f)) });
+}
+
+// `currentTarget` can't be set, but an own property on the event shadows the getter
+fn set_current_target(event: &web_sys::Event, target: Option<&web_sys::Node>) {
+	let key = JsValue::from_str("currentTarget");
+	if let Some(target) = target {
+		let descriptor = js_sys::Object::new();
+		js_sys::Reflect::set(&descriptor, &"value".into(), target).expect("can't set descriptor value");
+		js_sys::Reflect::set(&descriptor, &"configurable".into(), &true.into()).expect("can't set descriptor configurable");
+		js_sys::Reflect::define_property(event, &key, &descriptor).expect("can't define currentTarget");
+	} else {
+		js_sys::Reflect::delete_property(event, &key).expect("can't delete currentTarget");
+	}
+}
+
+fn dispatch(name: &'static str, event: &web_sys::Event) {
+	// unlike `target`, the path isn't retargeted at shadow roots, so handlers inside of them are reached too
+	// nodes hobo doesn't know about, like text nodes, are skipped
+	for node in event.composed_path().iter().filter_map(|x| x.dyn_into::<web_sys::Node>().ok()) {
+		let Some(entity) = Entity::from_node(&node) else { continue; };
+		let handlers = entity.try_get_cmp::<DelegatedHandlers>()
+			.and_then(|x| x.0.get(name).map(|handlers| handlers.iter().map(|x| (x.id, x.once, Rc::clone(&x.f))).collect::<Vec<_>>()))
+			.unwrap_or_default();
+		if handlers.is_empty() { continue; }
+
+		set_current_target(event, Some(&node));
+		for (id, once, f) in handlers {
+			// the handler is already running if it dispatched the same event itself, e.g. with `.click()`
+			let Ok(mut f) = f.try_borrow_mut() else { continue; };
+			f(event);
+			drop(f);
+			if once {
+				if let Some(mut handlers) = entity.try_get_cmp_mut::<DelegatedHandlers>() {
+					if let Some(handlers) = handlers.0.get_mut(name) { handlers.retain(|x| x.id != id); }
+				}
+			}
+		}
+		set_current_target(event, None);
+
+		// `cancel_bubble` is set by `stop_propagation`
+		if event.cancel_bubble() { break; }
+	}
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+fn attach_to_body(root: impl AsElement) {
+	let body = web_sys::window().expect("no window").document().expect("no document").body().expect("no body");
+	body.append_child(&root.get_cmp::<web_sys::Node>()).expect("can't append child");
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+fn record(log: &Rc<RefCell<Vec<&'static str>>>, name: &'static str) -> impl FnMut(web_sys::MouseEvent) + 'static {
+	let log = Rc::clone(log);
+	move |_| log.borrow_mut().push(name)
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn dispatches_from_the_target_up() {
+	use crate::create as e;
+
+	let log = Rc::new(RefCell::new(Vec::new()));
+	let span = e::span().delegate_events(true).on_click(record(&log, "span"));
+	let inner = e::div().delegate_events(true).on_click(record(&log, "inner")).child(span);
+	let outer = e::div().delegate_events(true).on_click(record(&log, "outer")).on_click(record(&log, "outer again")).child(inner);
+	// the element's own listener runs before the document's
+	let direct = e::div().delegate_events(false).on_click(record(&log, "direct")).child(outer);
+	let root = direct.allow_no_parent();
+	attach_to_body(root);
+
+	span.get_cmp::<web_sys::HtmlElement>().click();
+	assert_eq!(*log.borrow(), ["direct", "span", "inner", "outer", "outer again"]);
+	root.remove();
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn sets_current_target() {
+	use crate::create as e;
+
+	let events = Rc::new(RefCell::new(Vec::new()));
+	let record_event = || {
+		let events = Rc::clone(&events);
+		move |event: web_sys::MouseEvent| events.borrow_mut().push((event.current_target(), event))
+	};
+	let span = e::span().delegate_events(true).on_click(record_event());
+	let root = e::div().delegate_events(true).on_click(record_event()).child(span).allow_no_parent();
+	attach_to_body(root);
+
+	span.get_cmp::<web_sys::HtmlElement>().click();
+	let events = events.borrow();
+	let targets = events.iter().map(|(target, _)| target.clone().expect("no current target")).collect::<Vec<_>>();
+	assert_eq!(targets, [span.get_cmp::<web_sys::EventTarget>().clone(), root.get_cmp::<web_sys::EventTarget>().clone()]);
+	// the shadowing property is gone once the handlers are done
+	let (_, event) = &events[0];
+	assert!(!js_sys::Reflect::has_own_property(event, &"currentTarget".into()).expect("can't check currentTarget"));
+	root.remove();
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn stops_at_stop_propagation() {
+	use crate::create as e;
+
+	let log = Rc::new(RefCell::new(Vec::new()));
+	let span = e::span().delegate_events(true).on_click(record(&log, "span"));
+	let inner = e::div().delegate_events(true)
+		.on_click({ let log = Rc::clone(&log); move |e| { e.stop_propagation(); log.borrow_mut().push("inner"); } })
+		// handlers of the same element still run
+		.on_click(record(&log, "inner again"))
+		.child(span);
+	let root = e::div().delegate_events(true).on_click(record(&log, "outer")).child(inner).allow_no_parent();
+	attach_to_body(root);
+
+	span.get_cmp::<web_sys::HtmlElement>().click();
+	assert_eq!(*log.borrow(), ["span", "inner", "inner again"]);
+	root.remove();
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn reaches_into_shadow_roots() {
+	use crate::create as e;
+
+	let log = Rc::new(RefCell::new(Vec::new()));
+	let host = e::div().delegate_events(true).on_click(record(&log, "host")).allow_no_parent();
+	let span = e::span().delegate_events(true).on_click(record(&log, "span"));
+	host.attach_shadow(web_sys::ShadowRootMode::Open).add_child(span);
+	attach_to_body(host);
+
+	span.get_cmp::<web_sys::HtmlElement>().click();
+	assert_eq!(*log.borrow(), ["span", "host"]);
+	host.remove();
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn removes_once_handlers() {
+	use crate::create as e;
+
+	let log = Rc::new(RefCell::new(Vec::new()));
+	let root = e::div().delegate_events(true).on_click_once({ let log = Rc::clone(&log); move |_| log.borrow_mut().push("once") }).allow_no_parent();
+	attach_to_body(root);
+
+	root.get_cmp::<web_sys::HtmlElement>().click();
+	assert!(!has(root.as_entity(), "click"));
+	root.get_cmp::<web_sys::HtmlElement>().click();
+	assert_eq!(*log.borrow(), ["once"]);
+	root.remove();
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn skips_running_handlers() {
+	use crate::create as e;
+
+	let count = Rc::new(std::cell::Cell::new(0));
+	let span = e::span();
+	let root = e::div().delegate_events(true).child(span).allow_no_parent();
+	// the browser ignores `click()` on an element that is being clicked already, so the nested click starts at the child
+	root.add_on_click({
+		let count = Rc::clone(&count);
+		move |_| { count.set(count.get() + 1); span.get_cmp::<web_sys::HtmlElement>().click(); }
+	});
+	attach_to_body(root);
+
+	root.get_cmp::<web_sys::HtmlElement>().click();
+	assert_eq!(count.get(), 1);
+	root.remove();
+}
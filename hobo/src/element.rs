@@ -1,10 +1,16 @@
 use crate::prelude::*;
-use futures_signals::signal::{Signal, SignalExt};
+use crate::dom_events::EventHandler;
+use futures_signals::{
+	signal::{Mutable, MutableSignal, Signal, SignalExt},
+	signal_map::{MapDiff, SignalMap},
+	signal_vec::{SignalVec, SignalVecExt, VecDiff},
+};
 pub use hobo_derive::AsElement;
 use std::{
 	any::TypeId,
 	borrow::Cow,
-	collections::{HashMap, HashSet},
+	collections::{BTreeMap, HashMap, HashSet, VecDeque},
+	ops::Bound,
 };
 
 /// An `Element` with specific type erased
@@ -62,7 +68,7 @@ impl OnDomAttachCbs {
 }
 
 #[derive(Default)]
-struct SignalHandlesCollection(Vec<discard::DiscardOnDrop<futures_signals::CancelableFutureHandle>>);
+pub(crate) struct SignalHandlesCollection(pub(crate) Vec<discard::DiscardOnDrop<futures_signals::CancelableFutureHandle>>);
 
 // NOTE: This is so we can clear child signals on Children::clear
 // Kind of a hack, ideally we should be able to just steal components instead of replacing the entire entity
@@ -70,6 +76,80 @@ struct SignalHandlesCollection(Vec<discard::DiscardOnDrop<futures_signals::Cance
 #[derive(Default)]
 pub(crate) struct ChildSignalHandlesCollection(pub(crate) Vec<discard::DiscardOnDrop<futures_signals::CancelableFutureHandle>>);
 
+// a `Mutable` kept up to date by event handlers, shared between everyone who asks for its signal
+// the handlers live with the rest of the element's handlers
+pub(crate) struct Tracked<Tag, T> {
+	value: Mutable<T>,
+	_tag: std::marker::PhantomData<Tag>,
+}
+
+impl<Tag: 'static, T: Default + 'static> Tracked<Tag, T> {
+	pub(crate) fn get_or_init(
+		entity: Entity,
+		initial: impl FnOnce() -> T,
+		handlers: impl FnOnce(&web_sys::EventTarget, &Mutable<T>) -> Vec<EventHandler>,
+	) -> Mutable<T> {
+		if entity.is_dead() { log::warn!("tracked signal entity dead {:?}", entity); return Mutable::default(); }
+		if let Some(tracked) = entity.try_get_cmp::<Self>() { return tracked.value.clone(); }
+		if Element(entity).has_no_element("tracked signal") { return Mutable::default(); }
+		// nothing to track without a DOM, but the initial value is still known
+		if crate::ssr::ENABLED { return Mutable::new(initial()); }
+
+		let value = Mutable::new(initial());
+		let target = entity.get_cmp::<web_sys::EventTarget>().clone();
+		let handlers = handlers(&target, &value);
+		entity.get_cmp_mut_or_default::<Vec<EventHandler>>().extend(handlers);
+		entity.add_component(Self { value: value.clone(), _tag: std::marker::PhantomData });
+		value
+	}
+}
+
+// tested and different types with same byte-level representation hash to the same thing (not surprising)
+// i.e. the type is not taken into account when hashing so I have to do it manually
+pub(crate) fn tag_hash<Tag: std::hash::Hash + 'static>(tag: Tag) -> u64 {
+	use std::hash::{Hash, Hasher};
+	let mut hasher = std::collections::hash_map::DefaultHasher::new();
+	TypeId::of::<Tag>().hash(&mut hasher);
+	tag.hash(&mut hasher);
+	hasher.finish()
+}
+
+// the node that a portal child's nodes are attached to instead of its parent's, `None` on the server
+pub(crate) struct Portal(Option<web_sys::Node>);
+
+/// Where the DOM of a portal child goes, see `AsElement::add_portal_child`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PortalTarget {
+	Node(web_sys::Node),
+	/// `document.body`, unlike a `Node` it can be used on the server, where portal children aren't rendered
+	Body,
+}
+
+impl PortalTarget {
+	fn node(&self) -> Option<web_sys::Node> {
+		if crate::ssr::ENABLED { return None; }
+		match self {
+			Self::Node(x) => Some(x.clone()),
+			Self::Body => Some(web_sys::window().expect("no window").document().expect("no document").body().expect("no body").into()),
+		}
+	}
+}
+
+impl<T: AsRef<web_sys::Node>> From<&T> for PortalTarget {
+	fn from(x: &T) -> Self { Self::Node(x.as_ref().clone()) }
+}
+
+// portals and shadow roots are children whose nodes aren't in the parent's node
+pub(crate) fn outside_parent_dom(entity: Entity) -> bool { entity.has_cmp::<Portal>() || entity.has_cmp::<web_sys::ShadowRoot>() }
+
+// the element's content was set with `set_html_unchecked`, so its nodes aren't entities
+pub(crate) struct InnerHtml;
+
+struct Hover;
+struct Focus;
+struct FocusWithin;
+struct Pressed;
+
 #[allow(dead_code)]
 #[cfg(debug_assertions)]
 pub(crate) struct OrphanComplainer(i32, Closure<dyn Fn()>);
@@ -104,6 +184,59 @@ impl Drop for OrphanComplainer {
 }
 
 impl Element {
+	fn is_fragment(self) -> bool { self.has_cmp::<crate::create::FragmentMarker>() }
+
+	// fragments and shadow roots have no attributes, classes or styles of their own
+	pub(crate) fn has_no_element(self, method: &str) -> bool {
+		let res = self.is_fragment() || self.has_cmp::<web_sys::ShadowRoot>();
+		if res { log::warn!("{method} on a fragment or a shadow root {:?}", self.as_entity()); }
+		res
+	}
+	fn is_portal(self) -> bool { self.has_cmp::<Portal>() }
+
+	// whether this entity can take part in the DOM, either directly or through its children
+	fn has_dom(self) -> bool { self.has_cmp::<web_sys::Node>() || self.has_cmp::<crate::ssr::SsrNode>() || self.is_fragment() }
+
+	// DOM nodes that represent this entity, fragments are flattened into their children's nodes
+	fn dom_nodes(self) -> Vec<web_sys::Node> {
+		if let Some(node) = self.try_get_cmp::<web_sys::Node>() { return vec![node.clone()]; }
+		if !self.is_fragment() { return Vec::new(); }
+		let children = self.try_get_cmp::<Children>().map(|x| x.0.clone()).unwrap_or_default();
+		children.into_iter().filter(|&x| !outside_parent_dom(x)).flat_map(|x| Element(x).dom_nodes()).collect()
+	}
+
+	// the node that children of this entity are attached to, for fragments that is the nearest non-fragment ancestor's node
+	fn dom_parent(self) -> Option<web_sys::Node> {
+		if let Some(node) = self.try_get_cmp::<web_sys::Node>() { return Some(node.clone()); }
+		if !self.is_fragment() { return None; }
+		if let Some(portal) = self.try_get_cmp::<Portal>() { return portal.0.clone(); }
+		let parent = self.try_get_cmp::<Parent>()?.0;
+		Element(parent).dom_parent()
+	}
+
+	// the first DOM node that comes after this entity, to be used as reference for `insert_before`
+	// portals go at the end of their target
+	fn next_dom_sibling(self) -> Option<web_sys::Node> {
+		if self.is_portal() { return None; }
+		let parent = self.try_get_cmp::<Parent>()?.0;
+		let next = {
+			let siblings = parent.get_cmp::<Children>();
+			let position = siblings.iter().position(|&x| x == self.as_entity())?;
+			siblings[position + 1..].to_vec()
+		}.into_iter().filter(|&x| !outside_parent_dom(x)).find_map(|x| Element(x).dom_nodes().into_iter().next());
+
+		if next.is_none() && Element(parent).is_fragment() { Element(parent).next_dom_sibling() } else { next }
+	}
+
+	// insert nodes of a child that's already in `Children` into the DOM
+	fn attach_dom_nodes(self, child: Element, next: Option<&web_sys::Node>) {
+		// a fragment that isn't attached anywhere yet will have its nodes inserted once it is
+		let Some(parent_node) = self.dom_parent() else { return; };
+		for node in child.dom_nodes() {
+			parent_node.insert_before(&node, next).expect("can't append child");
+		}
+	}
+
 	#[track_caller]
 	fn add_child(self, child: Element) {
 		if self.is_dead() { log::warn!("add_child parent dead {:?}", self.as_entity()); return; }
@@ -112,18 +245,41 @@ impl Element {
 		self.get_cmp_mut_or_default::<Children>().push(child.as_entity());
 		child.get_cmp_mut_or_default::<Parent>().0 = self.as_entity();
 
-		if let (Some(parent_node), Some(child_node)) = (self.try_get_cmp::<web_sys::Node>(), child.try_get_cmp::<web_sys::Node>()) {
-			parent_node.append_child(&child_node).expect("can't append child");
+		if self.has_dom() && child.has_dom() {
+			// appending to a fragment has to go before whatever follows the fragment
+			let next = if self.is_fragment() { child.next_dom_sibling() } else { None };
+			self.attach_dom_nodes(child, next.as_ref());
 		} else {
 			let parent_has = if self.has_cmp::<web_sys::Node>() { "has" } else { "doesn't have" };
 			let child_has = if child.has_cmp::<web_sys::Node>() { "has" } else { "doesn't have" };
 			log::warn!("trying to add_child, but child {child_has} web_sys::Node and parent {parent_has} web_sys::Node");
 		}
 
-		#[cfg(debug_assertions)] {
-			let caller = std::panic::Location::caller();
-			child.set_attr("data-location", format!("{}:{}", caller.file(), caller.line()));
+		#[cfg(debug_assertions)] child.set_location();
+
+		#[cfg(feature = "experimental")]
+		OnDomAttachCbs::handle_parenting(self, child);
+
+		#[cfg(debug_assertions)]
+		child.remove_cmp::<OrphanComplainer>();
+	}
+
+	#[track_caller]
+	fn add_portal_child(self, target: &PortalTarget, child: Element) {
+		if self.is_dead() { log::warn!("add_portal_child parent dead {:?}", self.as_entity()); return; }
+		if child.is_dead() { log::warn!("add_portal_child child dead {:?}", child.as_entity()); return; }
+
+		self.get_cmp_mut_or_default::<Children>().push(child.as_entity());
+		child.get_cmp_mut_or_default::<Parent>().0 = self.as_entity();
+		let target = target.node();
+		if let Some(target) = &target {
+			for node in child.dom_nodes() {
+				target.append_child(&node).expect("can't append portal child");
+			}
 		}
+		child.add_component(Portal(target));
+
+		#[cfg(debug_assertions)] child.set_location();
 
 		#[cfg(feature = "experimental")]
 		OnDomAttachCbs::handle_parenting(self, child);
@@ -137,10 +293,12 @@ impl Element {
 		let parent = self.get_cmp::<Parent>().0;
 		if parent.is_dead() { log::warn!("leave_parent parent dead {:?}", self.as_entity()); return; }
 
-		if let (Some(parent_node), Some(child_node)) = (parent.try_get_cmp::<web_sys::Node>(), self.try_get_cmp::<web_sys::Node>()) {
-			parent_node.remove_child(&child_node).expect("can't remove child");
+		// the nodes aren't necessarily in the parent's node, e.g. for portals or children of a shadow root
+		for child_node in self.dom_nodes() {
+			if let Some(parent_node) = child_node.parent_node() { parent_node.remove_child(&child_node).expect("can't remove child"); }
 		}
 
+		self.remove_cmp::<Portal>();
 		self.remove_cmp::<Parent>();
 		let mut siblings = parent.get_cmp_mut::<Children>();
 		if let Some(child_pos) = siblings.0.iter().position(|&x| x == self.as_entity()) {
@@ -153,63 +311,256 @@ impl Element {
 		if self.is_dead() { log::warn!("add_child_at parent dead {:?}", self.as_entity()); return; }
 		if child.is_dead() { log::warn!("add_child_at child dead {:?}", child.as_entity()); return; }
 
-		let mut children = self.get_cmp_mut_or_default::<Children>();
-		let shifted_sibling = children.get(at_index).copied();
-		children.insert(at_index, child.as_entity());
+		self.get_cmp_mut_or_default::<Children>().insert(at_index, child.as_entity());
 		child.get_cmp_mut_or_default::<Parent>().0 = self.as_entity();
 
-		if let (Some(parent_node), Some(child_node), shifted_sibling_node) = (
-			self.try_get_cmp::<web_sys::Node>(),
-			child.try_get_cmp::<web_sys::Node>(),
-			shifted_sibling.and_then(|x| x.try_get_cmp::<web_sys::Node>()),
-		) {
-			parent_node
-				.insert_before(&child_node, shifted_sibling_node.as_ref().map(|x| &**x as &web_sys::Node))
-				.expect("can't append child");
+		if self.has_dom() && child.has_dom() {
+			let next = child.next_dom_sibling();
+			self.attach_dom_nodes(child, next.as_ref());
 
-
-			#[cfg(debug_assertions)] {
-				let caller = std::panic::Location::caller();
-				child.set_attr("data-location", format!("{}:{}", caller.file(), caller.line()));
-			}
+			#[cfg(debug_assertions)] child.set_location();
 		}
+
+		#[cfg(feature = "experimental")]
+		OnDomAttachCbs::handle_parenting(self, child);
+
+		#[cfg(debug_assertions)]
+		child.remove_cmp::<OrphanComplainer>();
+	}
+
+	#[cfg(debug_assertions)]
+	#[track_caller]
+	fn set_location(self) {
+		// anchors don't have attributes
+		if !self.has_cmp::<web_sys::Element>() { return; }
+		let caller = std::panic::Location::caller();
+		self.set_attr("data-location", format!("{}:{}", caller.file(), caller.line()));
 	}
 
 	// this track_caller doesn't work exactly how I'd want, the `data-location` attr for the child is set to the `.replace_with` line
 	#[track_caller]
 	fn add_child_signal<S>(self, signal: S) where
-		S: Signal<Item = Element> + 'static,
+		S: Signal<Item = Option<Element>> + 'static,
 	{
-		// placeholder at first
-		let mut child = crate::create::div().class(crate::css::display::none).as_element();
-		self.add_child(child);
+		// the anchor stays in place and the current child, if any, sits right before it
+		let anchor = Element(crate::create::anchor("child_signal").0);
+		self.add_child(anchor);
+		let mut child = None::<Element>;
 		let (handle, fut) = futures_signals::cancelable_future(signal.for_each(move |new_child| {
-			let new_child = new_child.as_element();
-			child.replace_with(new_child);
+			match (child, new_child) {
+				(Some(old), Some(new)) => old.replace_with(new),
+				(Some(old), None) => old.remove(),
+				(None, Some(new)) => self.add_child_before(anchor, new),
+				(None, None) => {},
+			}
 			child = new_child;
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
+		self.get_cmp_mut_or_default::<ChildSignalHandlesCollection>().0.push(handle);
+	}
+
+	fn add_children_signal_vec<S>(self, signal_vec: S) where
+		S: SignalVec<Item = Element> + 'static,
+	{
+		// anchor marks the end of the list, items are always inserted before it
+		let end = Element(crate::create::anchor("children_signal_vec").0);
+		self.add_child(end);
+		let mut items = Vec::<Element>::new();
+		let handle = signal_vec.subscribe(move |diff| {
+			if self.is_dead() { log::warn!("children_signal_vec parent dead {:?}", self.as_entity()); return; }
+			match diff {
+				VecDiff::Replace { values } => {
+					for item in items.drain(..) { item.remove(); }
+					for value in values {
+						self.insert_list_item(end, &items, items.len(), value);
+						items.push(value);
+					}
+				},
+				VecDiff::InsertAt { index, value } => {
+					self.insert_list_item(end, &items, index, value);
+					items.insert(index, value);
+				},
+				VecDiff::UpdateAt { index, value } => {
+					items[index].replace_with(value);
+					items[index] = value;
+				},
+				VecDiff::RemoveAt { index } => items.remove(index).remove(),
+				VecDiff::Move { old_index, new_index } => {
+					let item = items.remove(old_index);
+					item.leave_parent();
+					self.insert_list_item(end, &items, new_index, item);
+					items.insert(new_index, item);
+				},
+				VecDiff::Push { value } => {
+					self.insert_list_item(end, &items, items.len(), value);
+					items.push(value);
+				},
+				VecDiff::Pop {} => if let Some(item) = items.pop() { item.remove(); },
+				VecDiff::Clear {} => for item in items.drain(..) { item.remove(); },
+			}
+		});
+
+		self.get_cmp_mut_or_default::<ChildSignalHandlesCollection>().0.push(handle);
+	}
+
+	fn add_children_signal_vec_keyed<S, K>(self, signal_vec: S, mut key: impl FnMut(&S::Item) -> K + 'static, mut f: impl FnMut(S::Item) -> Element + 'static) where
+		S: SignalVec + 'static,
+		K: Eq + std::hash::Hash + 'static,
+	{
+		let end = Element(crate::create::anchor("children_signal_vec_keyed").0);
+		self.add_child(end);
+		let mut keys = Vec::<K>::new();
+		let mut items = Vec::<Element>::new();
+		let handle = signal_vec.subscribe(move |diff| {
+			if self.is_dead() { log::warn!("children_signal_vec_keyed parent dead {:?}", self.as_entity()); return; }
+			match diff {
+				VecDiff::Replace { values } => {
+					// duplicate keys are reused in order
+					let mut old = HashMap::<K, VecDeque<Element>>::new();
+					for (k, item) in keys.drain(..).zip(items.drain(..)) { old.entry(k).or_default().push_back(item); }
+					let mut reused = HashSet::new();
+					for value in values {
+						let k = key(&value);
+						let item = old.get_mut(&k).and_then(VecDeque::pop_front).map_or_else(|| f(value), |item| { reused.insert(item); item });
+						keys.push(k);
+						items.push(item);
+					}
+					for item in old.into_values().flatten() { item.remove(); }
+
+					// walk backwards so that reused items which are already in the right order stay where they are
+					// `position` is where `next` is, items that aren't in place yet are all before it
+					let mut position = self.get_cmp::<Children>().iter().position(|&x| x == end.as_entity()).expect("anchor missing in parent");
+					for &item in items.iter().rev() {
+						if reused.contains(&item) {
+							let in_place = position > 0 && self.get_cmp::<Children>()[position - 1] == item.as_entity();
+							if in_place { position -= 1; continue; }
+							item.leave_parent();
+							position -= 1;
+						}
+						Element::add_child_at(self, position, item);
+					}
+				},
+				VecDiff::InsertAt { index, value } => {
+					keys.insert(index, key(&value));
+					let item = f(value);
+					self.insert_list_item(end, &items, index, item);
+					items.insert(index, item);
+				},
+				VecDiff::UpdateAt { index, value } => {
+					let k = key(&value);
+					if keys[index] != k {
+						let item = f(value);
+						items[index].replace_with(item);
+						items[index] = item;
+						keys[index] = k;
+					}
+				},
+				VecDiff::RemoveAt { index } => {
+					keys.remove(index);
+					items.remove(index).remove();
+				},
+				VecDiff::Move { old_index, new_index } => {
+					let k = keys.remove(old_index);
+					keys.insert(new_index, k);
+					let item = items.remove(old_index);
+					item.leave_parent();
+					self.insert_list_item(end, &items, new_index, item);
+					items.insert(new_index, item);
+				},
+				VecDiff::Push { value } => {
+					keys.push(key(&value));
+					let item = f(value);
+					self.insert_list_item(end, &items, items.len(), item);
+					items.push(item);
+				},
+				VecDiff::Pop {} => {
+					keys.pop();
+					if let Some(item) = items.pop() { item.remove(); }
+				},
+				VecDiff::Clear {} => {
+					keys.clear();
+					for item in items.drain(..) { item.remove(); }
+				},
+			}
+		});
+
+		self.get_cmp_mut_or_default::<ChildSignalHandlesCollection>().0.push(handle);
+	}
+
+	fn add_children_signal_map<S>(self, signal_map: S, mut f: impl FnMut(&S::Key, S::Value) -> Element + 'static) where
+		S: SignalMap + 'static,
+		S::Key: Ord + 'static,
+	{
+		// anchor marks the end of the map, entries are inserted before it or before the next key
+		let end = Element(crate::create::anchor("children_signal_map").0);
+		self.add_child(end);
+		let mut items = BTreeMap::<S::Key, Element>::new();
+		let handle = signal_map.subscribe(move |diff| {
+			if self.is_dead() { log::warn!("children_signal_map parent dead {:?}", self.as_entity()); return; }
+			match diff {
+				MapDiff::Replace { entries } => {
+					for (_, item) in std::mem::take(&mut items) { item.remove(); }
+					for (key, value) in entries {
+						let item = f(&key, value);
+						self.add_child_before(end, item);
+						items.insert(key, item);
+					}
+				},
+				MapDiff::Insert { key, value } | MapDiff::Update { key, value } => {
+					let item = f(&key, value);
+					if let Some(old) = items.get_mut(&key) {
+						old.replace_with(item);
+						*old = item;
+					} else {
+						let anchor = items.range((Bound::Excluded(&key), Bound::Unbounded)).next().map_or(end, |(_, &x)| x);
+						self.add_child_before(anchor, item);
+						items.insert(key, item);
+					}
+				},
+				MapDiff::Remove { key } => if let Some(item) = items.remove(&key) { item.remove(); },
+				MapDiff::Clear {} => for (_, item) in std::mem::take(&mut items) { item.remove(); },
+			}
+		});
+
 		self.get_cmp_mut_or_default::<ChildSignalHandlesCollection>().0.push(handle);
 	}
 
+	// insert `child` so that it ends up at `index` among `items`, which are followed by `end` in `Children`
+	fn insert_list_item(self, end: Element, items: &[Element], index: usize, child: Element) {
+		self.add_child_before(items.get(index).copied().unwrap_or(end), child);
+	}
+
+	fn add_child_before(self, anchor: Element, child: Element) {
+		let position = self.get_cmp::<Children>().iter().position(|&x| x == anchor.as_entity()).expect("anchor missing in parent");
+		Element::add_child_at(self, position, child);
+	}
+
 	#[track_caller]
 	fn replace_with(self, other: Element) {
 		if self.is_dead() { log::warn!("replace_with dead {:?}", self.as_entity()); return; }
 
-		if let (Some(this), Some(other)) = (self.try_get_cmp::<web_sys::Element>(), other.try_get_cmp::<web_sys::Node>()) {
-			this.replace_with_with_node_1(&other).unwrap();
+		if self.has_dom() && other.has_dom() {
+			// other's nodes go where self's nodes are, self's nodes are removed along with self
+			// portals are placed relative to their own nodes since they aren't in the parent's node
+			let parent = self.try_get_cmp::<Parent>().map(|x| x.0).filter(|_| !self.is_portal());
+			let (parent_node, next) = parent.map_or_else(|| {
+				let nodes = self.dom_nodes();
+				(nodes.first().and_then(web_sys::Node::parent_node), nodes.last().and_then(web_sys::Node::next_sibling))
+			}, |parent| (Element(parent).dom_parent(), self.next_dom_sibling()));
+			if let Some(parent_node) = parent_node {
+				for node in other.dom_nodes() {
+					parent_node.insert_before(&node, next.as_ref()).expect("can't replace child");
+				}
+			}
 		} else {
 			let self_has = if self.has_cmp::<web_sys::Node>() { "has" } else { "doesn't have" };
 			let other_has = if other.has_cmp::<web_sys::Node>() { "has" } else { "doesn't have" };
 			log::warn!("trying to replace_with, but self {self_has} web_sys::Node and other {other_has} web_sys::Node");
 		}
 
-		#[cfg(debug_assertions)] {
-			let caller = std::panic::Location::caller();
-			other.set_attr("data-location", format!("{}:{}", caller.file(), caller.line()));
-		}
+		#[cfg(debug_assertions)] other.set_location();
 
 		// Fix up reference in parent
 		if let Some(parent) = self.try_get_cmp::<Parent>().map(|x| x.0) {
@@ -220,6 +571,8 @@ impl Element {
 				children.0[position] = other.as_entity();
 				other.get_cmp_mut_or_default::<Parent>().0 = parent;
 			}
+			let portal = self.try_get_cmp::<Portal>().map(|x| x.0.clone());
+			if let Some(target) = portal { other.add_component(Portal(target)); }
 
 			#[cfg(debug_assertions)]
 			other.remove_cmp::<OrphanComplainer>();
@@ -262,6 +615,33 @@ pub trait AsElement: AsEntity + Sized {
 	#[track_caller] #[must_use] fn children<Item: AsElement>(self, children: impl IntoIterator<Item = Item>) -> Self { self.add_children(children); self }
 	fn leave_parent(self) { Element::leave_parent(self.as_element()) }
 
+	/// Add a child that's removed together with `self` and found among its descendants, but whose DOM is attached to `target`,
+	/// e.g. a modal or a tooltip that should be in `document.body`
+	///
+	/// `target` is a `PortalTarget` or a reference to a `web_sys` node. On the server portal children aren't rendered.
+	#[track_caller]
+	fn add_portal_child<T: AsElement>(&self, target: impl Into<PortalTarget>, child: T) {
+		#[cfg(feature = "experimental")]
+		if let Some(mark) = T::MARK { child.get_cmp_mut_or_default::<Classes>().marks.insert(mark()); }
+
+		#[cfg(all(debug_assertions, feature = "experimental"))]
+		if let Some(type_id) = T::TYPE { child.set_attr("data-type", type_id()); }
+
+		Element::add_portal_child(self.as_element(), &target.into(), child.as_element());
+	}
+	#[track_caller] #[must_use] fn portal_child(self, target: impl Into<PortalTarget>, child: impl AsElement) -> Self { self.add_portal_child(target, child); self }
+
+	/// Attach a shadow root, children added to it are rendered instead of the element's own and isolated from the styles of the page
+	fn attach_shadow(&self, mode: web_sys::ShadowRootMode) -> crate::create::ShadowRoot {
+		let root = crate::create::shadow_root(self.as_entity(), mode);
+		if crate::ssr::ENABLED { Element::add_child(self.as_element(), root.as_element()); return root; }
+
+		// owned by the element, but it isn't one of its child nodes
+		self.get_cmp_mut_or_default::<Children>().push(root.as_entity());
+		root.get_cmp_mut_or_default::<Parent>().0 = self.as_entity();
+		root
+	}
+
 	/// add a child at an index, useful to update tables without regenerating the whole container element
 	#[track_caller]
 	fn add_child_at<T: AsElement>(&self, at_index: usize, child: T) {
@@ -287,7 +667,7 @@ pub trait AsElement: AsEntity + Sized {
 			#[cfg(all(debug_assertions, feature = "experimental"))]
 			if let Some(type_id) = E::TYPE { x.set_attr("data-type", type_id()); }
 
-			x.as_element()
+			Some(x.as_element())
 		}));
 	}
 	#[track_caller]
@@ -297,22 +677,120 @@ pub trait AsElement: AsEntity + Sized {
 		S: Signal<Item = E> + 'static,
 	{ self.add_child_signal(signal); self }
 
+	/// same as `add_child_signal`, but `None` renders nothing at all
+	#[track_caller]
+	fn add_child_signal_option<S, E>(&self, signal: S) where
+		E: AsElement,
+		S: Signal<Item = Option<E>> + 'static,
+	{
+		Element::add_child_signal(self.as_element(), signal.map(|x| x.map(|x| {
+			#[cfg(feature = "experimental")]
+			if let Some(mark) = E::MARK { x.get_cmp_mut_or_default::<Classes>().marks.insert(mark()); }
+
+			#[cfg(all(debug_assertions, feature = "experimental"))]
+			if let Some(type_id) = E::TYPE { x.set_attr("data-type", type_id()); }
+
+			x.as_element()
+		})));
+	}
+	#[track_caller]
+	#[must_use]
+	fn child_signal_option<S, E>(self, signal: S) -> Self where
+		E: AsElement,
+		S: Signal<Item = Option<E>> + 'static,
+	{ self.add_child_signal_option(signal); self }
+
+	/// render a child for every item of a `SignalVec`, applying each `VecDiff` to `Children` and the DOM directly
+	///
+	/// removed items have their entities removed, moved items keep theirs
+	fn add_children_signal_vec<S, E, F>(&self, signal_vec: S, mut f: F) where
+		E: AsElement,
+		S: SignalVec + 'static,
+		F: FnMut(S::Item) -> E + 'static,
+	{
+		Element::add_children_signal_vec(self.as_element(), signal_vec.map(move |x| {
+			let x = f(x);
+
+			#[cfg(feature = "experimental")]
+			if let Some(mark) = E::MARK { x.get_cmp_mut_or_default::<Classes>().marks.insert(mark()); }
+
+			#[cfg(all(debug_assertions, feature = "experimental"))]
+			if let Some(type_id) = E::TYPE { x.set_attr("data-type", type_id()); }
+
+			x.as_element()
+		}));
+	}
+	#[must_use]
+	fn children_signal_vec<S, E, F>(self, signal_vec: S, f: F) -> Self where
+		E: AsElement,
+		S: SignalVec + 'static,
+		F: FnMut(S::Item) -> E + 'static,
+	{ self.add_children_signal_vec(signal_vec, f); self }
+
+	/// same as `add_children_signal_vec`, but items are identified by `key`,
+	/// so entities are reused rather than rebuilt when the vec is replaced or an item is updated with the same key
+	fn add_children_signal_vec_keyed<S, K, E, F>(&self, signal_vec: S, key: impl FnMut(&S::Item) -> K + 'static, mut f: F) where
+		E: AsElement,
+		K: Eq + std::hash::Hash + 'static,
+		S: SignalVec + 'static,
+		F: FnMut(S::Item) -> E + 'static,
+	{
+		Element::add_children_signal_vec_keyed(self.as_element(), signal_vec, key, move |x| {
+			let x = f(x);
+
+			#[cfg(feature = "experimental")]
+			if let Some(mark) = E::MARK { x.get_cmp_mut_or_default::<Classes>().marks.insert(mark()); }
+
+			#[cfg(all(debug_assertions, feature = "experimental"))]
+			if let Some(type_id) = E::TYPE { x.set_attr("data-type", type_id()); }
+
+			x.as_element()
+		});
+	}
+	#[must_use]
+	fn children_signal_vec_keyed<S, K, E, F>(self, signal_vec: S, key: impl FnMut(&S::Item) -> K + 'static, f: F) -> Self where
+		E: AsElement,
+		K: Eq + std::hash::Hash + 'static,
+		S: SignalVec + 'static,
+		F: FnMut(S::Item) -> E + 'static,
+	{ self.add_children_signal_vec_keyed(signal_vec, key, f); self }
+
+	/// render a child for every entry of a `SignalMap`, kept in key order
+	///
+	/// each `MapDiff` only inserts, replaces or removes the child of the affected key
+	fn add_children_signal_map<S, E, F>(&self, signal_map: S, mut f: F) where
+		E: AsElement,
+		S: SignalMap + 'static,
+		S::Key: Ord + 'static,
+		F: FnMut(&S::Key, S::Value) -> E + 'static,
+	{
+		Element::add_children_signal_map(self.as_element(), signal_map, move |k, v| {
+			let x = f(k, v);
+
+			#[cfg(feature = "experimental")]
+			if let Some(mark) = E::MARK { x.get_cmp_mut_or_default::<Classes>().marks.insert(mark()); }
+
+			#[cfg(all(debug_assertions, feature = "experimental"))]
+			if let Some(type_id) = E::TYPE { x.set_attr("data-type", type_id()); }
+
+			x.as_element()
+		});
+	}
+	#[must_use]
+	fn children_signal_map<S, E, F>(self, signal_map: S, f: F) -> Self where
+		E: AsElement,
+		S: SignalMap + 'static,
+		S::Key: Ord + 'static,
+		F: FnMut(&S::Key, S::Value) -> E + 'static,
+	{ self.add_children_signal_map(signal_map, f); self }
+
 	fn set_class_tagged<Tag: std::hash::Hash + 'static>(&self, tag: Tag, style: impl Into<css::Style>) {
 		if self.is_dead() { log::warn!("set_class_tagged dead {:?}", self.as_entity()); return; }
-
-		// tested and different types with same byte-level representation hash to the same thing (not surprising)
-		// i.e. the type is not taken into account when hashing so I have to do it manually
-		let tag_hash = {
-			use std::hash::{Hash, Hasher};
-			let mut hasher = std::collections::hash_map::DefaultHasher::new();
-			TypeId::of::<Tag>().hash(&mut hasher);
-			tag.hash(&mut hasher);
-			hasher.finish()
-		};
+		if self.as_element().has_no_element("set_class_tagged") { return; }
 
 		let mut classes = self.get_cmp_mut_or_default::<Classes>();
 		let len = classes.styles.len();
-		classes.styles.insert(tag_hash, (style.into(), len));
+		classes.styles.insert(tag_hash(tag), (style.into(), len));
 	}
 	fn set_class_typed<Type: 'static>(&self, style: impl Into<css::Style>) { self.set_class_tagged(TypeId::of::<Type>(), style) }
 	fn set_class(&self, style: impl Into<css::Style>) { self.set_class_tagged(0u64, style); }
@@ -335,7 +813,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -356,7 +834,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -378,7 +856,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -388,15 +866,32 @@ pub trait AsElement: AsEntity + Sized {
 		S: Signal<Item = I> + 'static,
 	{ self.set_class_tagged_signal::<Tag, S, I>(tag, signal); self }
 
+	/// Run a Web Animations API animation on the element, it's cancelled when the element is removed
+	fn animate(&self, keyframes: impl AsRef<[crate::animation::Keyframe]>, options: &crate::animation::AnimationOptions) -> crate::animation::AnimationHandle {
+		crate::animation::animate(self.as_element(), keyframes.as_ref(), options)
+	}
+
+	/// Apply `enter` when the element is built and `leave` when it's removed, its node stays until the animation or transition ends
+	///
+	/// `duration` is how long to wait in case no animation or transition ends, e.g. because the element isn't visible.
+	fn set_transition(&self, enter: impl Into<css::Style>, leave: impl Into<css::Style>, duration: std::time::Duration) {
+		crate::transition::set(self.as_element(), enter.into(), leave.into(), duration);
+	}
+	#[must_use] fn transition(self, enter: impl Into<css::Style>, leave: impl Into<css::Style>, duration: std::time::Duration) -> Self { self.set_transition(enter, leave, duration); self }
+
 	fn get_attr<'k>(&self, key: impl Into<Cow<'k, str>>) -> Option<String> {
 		if self.is_dead() { log::warn!("get_attr dead {:?}", self.as_entity()); return None; }
+		if self.as_element().has_no_element("get_attr") { return None; }
 		let key = key.into();
+		if crate::ssr::ENABLED { return crate::ssr::get_attr(self.as_entity(), &key); }
 		self.get_cmp::<web_sys::Element>().get_attribute(&key)
 	}
 	fn set_attr<'k, 'v>(&self, key: impl Into<Cow<'k, str>>, value: impl Into<Cow<'v, str>>) {
 		if self.is_dead() { log::warn!("set_attr dead {:?}", self.as_entity()); return; }
+		if self.as_element().has_no_element("set_attr") { return; }
 		let key = key.into();
 		let value = value.into();
+		if crate::ssr::ENABLED { crate::ssr::set_attr(self.as_entity(), &key, &value); return; }
 		self.get_cmp::<web_sys::Element>().set_attribute(&key, &value).unwrap_or_else(|_| panic!("can't set attribute {} to {}", key, value));
 	}
 	#[must_use] fn attr<'k, 'v>(self, key: impl Into<Cow<'k, str>>, value: impl Into<Cow<'v, str>>) -> Self { self.set_attr(key, value); self }
@@ -404,6 +899,8 @@ pub trait AsElement: AsEntity + Sized {
 	#[must_use] fn bool_attr<'k>(self, key: impl Into<Cow<'k, str>>, value: bool) -> Self { self.set_bool_attr(key, value); self }
 	fn remove_attr<'k>(&self, key: impl Into<Cow<'k, str>>) {
 		if self.is_dead() { log::warn!("remove_attr dead {:?}", self.as_entity()); return; }
+		if self.as_element().has_no_element("remove_attr") { return; }
+		if crate::ssr::ENABLED { crate::ssr::remove_attr(self.as_entity(), &key.into()); return; }
 		self.get_cmp::<web_sys::Element>().remove_attribute(&key.into()).expect("can't remove attribute");
 	}
 
@@ -420,7 +917,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -442,7 +939,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -451,8 +948,51 @@ pub trait AsElement: AsEntity + Sized {
 		S: Signal<Item = bool> + 'static,
 	{ self.set_bool_attr_signal(attr, signal); self }
 
+	/// Properties like `checked`, `indeterminate` or `scrollTop` are what the element actually uses, while attributes often only set the initial state
+	///
+	/// Properties aren't rendered on the server.
+	fn get_prop<'k>(&self, key: impl Into<Cow<'k, str>>) -> JsValue {
+		if self.is_dead() { log::warn!("get_prop dead {:?}", self.as_entity()); return JsValue::UNDEFINED; }
+		if self.as_element().has_no_element("get_prop") { return JsValue::UNDEFINED; }
+		if crate::ssr::ENABLED { return JsValue::UNDEFINED; }
+		js_sys::Reflect::get(&self.get_cmp::<web_sys::Element>(), &JsValue::from_str(&key.into())).unwrap_or(JsValue::UNDEFINED)
+	}
+	fn set_prop<'k>(&self, key: impl Into<Cow<'k, str>>, value: impl Into<JsValue>) {
+		if self.is_dead() { log::warn!("set_prop dead {:?}", self.as_entity()); return; }
+		if self.as_element().has_no_element("set_prop") { return; }
+		if crate::ssr::ENABLED { return; }
+		let key = key.into();
+		js_sys::Reflect::set(&self.get_cmp::<web_sys::Element>(), &JsValue::from_str(&key), &value.into()).unwrap_or_else(|_| panic!("can't set property {key}"));
+	}
+	#[must_use] fn prop<'k>(self, key: impl Into<Cow<'k, str>>, value: impl Into<JsValue>) -> Self { self.set_prop(key, value); self }
+
+	fn set_prop_signal<'k, S, K, V>(&self, key: K, signal: S) where
+		K: Into<Cow<'k, str>>,
+		V: Into<JsValue>,
+		S: Signal<Item = V> + 'static,
+	{
+		let entity = self.as_entity();
+		if entity.is_dead() { log::warn!("set_prop_signal dead entity {:?}", entity); return; }
+		let key = key.into().into_owned();
+		let (handle, fut) = futures_signals::cancelable_future(signal.for_each(move |v| {
+			Element(entity).set_prop(&key, v);
+			std::future::ready(())
+		}), Default::default);
+
+		crate::signals_ext::spawn(fut);
+		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+	}
+	#[must_use]
+	fn prop_signal<'k, S, K, V>(self, key: K, signal: S) -> Self where
+		K: Into<Cow<'k, str>>,
+		V: Into<JsValue>,
+		S: Signal<Item = V> + 'static,
+	{ self.set_prop_signal(key, signal); self }
+
 	fn set_text<'a>(&self, text: impl Into<std::borrow::Cow<'a, str>>) {
 		if self.is_dead() { log::warn!("set_text dead entity {:?}", self.as_entity()); return; }
+		if self.as_element().has_no_element("set_text") { return; }
+		if crate::ssr::ENABLED { crate::ssr::set_text(self.as_entity(), &text.into()); return; }
 		self.get_cmp::<web_sys::Node>().set_text_content(Some(&text.into()));
 	}
 	#[must_use] fn text<'a>(self, x: impl Into<std::borrow::Cow<'a, str>>) -> Self { self.set_text(x); self }
@@ -468,7 +1008,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -477,6 +1017,40 @@ pub trait AsElement: AsEntity + Sized {
 		S: Signal<Item = I> + 'static,
 	{ self.set_text_signal(x); self }
 
+	/// Replace the content of the element with markup that went through `hobo::sanitize`
+	///
+	/// Children are removed first, except for portals and shadow roots.
+	fn set_html(&self, html: &crate::sanitize::SanitizedHtml) { self.set_html_unchecked(html.as_str()); }
+	#[must_use] fn html(self, html: &crate::sanitize::SanitizedHtml) -> Self { self.set_html(html); self }
+
+	/// Same as `set_html`, but nothing stops the markup from running scripts, so it must come from a trusted source
+	fn set_html_unchecked(&self, html: &str) {
+		let entity = self.as_entity();
+		if entity.is_dead() { log::warn!("set_html dead entity {:?}", entity); return; }
+		if self.as_element().has_no_element("set_html") { return; }
+		// `child_signal` and the like would keep inserting children next to anchors that are gone
+		entity.remove_cmp::<ChildSignalHandlesCollection>();
+		let children = entity.try_get_cmp::<Children>().map(|x| x.0.clone()).unwrap_or_default();
+		for child in children.into_iter().filter(|&x| !outside_parent_dom(x)) { child.remove(); }
+		if !entity.has_cmp::<InnerHtml>() { entity.add_component(InnerHtml); }
+
+		if crate::ssr::ENABLED { crate::ssr::set_html(entity, html); return; }
+		self.get_cmp::<web_sys::Element>().set_inner_html(html);
+	}
+
+	fn set_html_signal<S: Signal<Item = crate::sanitize::SanitizedHtml> + 'static>(&self, signal: S) {
+		let entity = self.as_entity();
+		if entity.is_dead() { log::warn!("set_html_signal dead entity {:?}", entity); return; }
+		let (handle, fut) = futures_signals::cancelable_future(signal.for_each(move |html| {
+			Element(entity).set_html(&html);
+			std::future::ready(())
+		}), Default::default);
+
+		crate::signals_ext::spawn(fut);
+		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+	}
+	#[must_use] fn html_signal<S: Signal<Item = crate::sanitize::SanitizedHtml> + 'static>(self, signal: S) -> Self { self.set_html_signal(signal); self }
+
 	fn set_style(&self, style: impl AppendProperty) {
 		let mut props = Vec::new();
 		style.append_property(&mut props);
@@ -496,7 +1070,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 	}
 	#[must_use]
@@ -508,12 +1082,14 @@ pub trait AsElement: AsEntity + Sized {
 	#[allow(clippy::return_self_not_must_use)]
 	fn mark<T: 'static>(self) -> Self {
 		if self.is_dead() { log::warn!("mark dead {:?}", self.as_entity()); return self; }
+		if self.as_element().has_no_element("mark") { return self; }
 		self.get_cmp_mut_or_default::<Classes>().marks.insert(TypeId::of::<T>());
 		self
 	}
 	#[allow(clippy::return_self_not_must_use)]
 	fn unmark<T: 'static>(self) -> Self {
 		if self.is_dead() { log::warn!("unmark dead {:?}", self.as_entity()); return self; }
+		if self.as_element().has_no_element("unmark") { return self; }
 		self.get_cmp_mut_or_default::<Classes>().marks.remove(&TypeId::of::<T>());
 		self
 	}
@@ -528,7 +1104,7 @@ pub trait AsElement: AsEntity + Sized {
 			std::future::ready(())
 		}), Default::default);
 
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
 		self
 	}
@@ -550,7 +1126,7 @@ pub trait AsElement: AsEntity + Sized {
 
 	fn parent(&self) -> Element {
 		let parent = self.get_cmp::<Parent>().0;
-		debug_assert!(parent.try_get_cmp::<web_sys::HtmlElement>().is_some());
+		debug_assert!(parent.try_get_cmp::<web_sys::HtmlElement>().is_some() || parent.has_cmp::<crate::ssr::SsrNode>() || Element(parent).is_fragment());
 		Element(parent)
 	}
 
@@ -570,8 +1146,307 @@ pub trait AsElement: AsEntity + Sized {
 	#[must_use]
 	fn allow_no_parent(self) -> Self { Element::allow_no_parent(self.as_element()); self }
 
-	fn blur(&self) { self.get_cmp::<web_sys::HtmlElement>().blur().ok(); }
+	fn blur(&self) {
+		if crate::ssr::ENABLED || self.as_element().has_no_element("blur") { return; }
+		self.get_cmp::<web_sys::HtmlElement>().blur().ok();
+	}
+	fn focus(&self) {
+		if crate::ssr::ENABLED || self.as_element().has_no_element("focus") { return; }
+		self.get_cmp::<web_sys::HtmlElement>().focus().ok();
+	}
+	/// Focus the element once it's attached, which is expected to happen right after it's built
+	#[must_use] fn autofocus(self) -> Self {
+		if crate::ssr::ENABLED { return self; }
+		crate::focus::focus_when_attached(self.as_entity());
+		self
+	}
+
+	/// Focus the element when the signal is `true` and blur it when it's `false`, see `autofocus`
+	fn set_focused_signal<S: Signal<Item = bool> + 'static>(&self, signal: S) {
+		let entity = self.as_entity();
+		if entity.is_dead() { log::warn!("set_focused_signal dead entity {:?}", entity); return; }
+		if crate::ssr::ENABLED { return; }
+		let (handle, fut) = futures_signals::cancelable_future(signal.for_each(move |focused| {
+			if focused {
+				crate::focus::focus_when_attached(entity);
+			} else if active_element().is_some_and(|x| x == *entity.get_cmp::<web_sys::Element>()) {
+				Element(entity).blur();
+			}
+			std::future::ready(())
+		}), Default::default);
+
+		crate::signals_ext::spawn(fut);
+		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+	}
+	#[must_use] fn focused_signal<S: Signal<Item = bool> + 'static>(self, signal: S) -> Self { self.set_focused_signal(signal); self }
+
+	/// Keep Tab and Shift+Tab within the element's descendants until it's removed, see `focus::FocusTrap`
+	fn add_focus_trap(&self) { crate::focus::add_focus_trap(self.as_element()); }
+	#[must_use] fn focus_trap(self) -> Self { self.add_focus_trap(); self }
+
+	/// Make the element's focusable descendants a single tab stop, arrow keys along `orientation` as well as Home and End move between them
+	fn add_roving_tabindex(&self, orientation: crate::aria::Orientation) { crate::focus::add_roving_tabindex(self.as_element(), orientation); }
+	#[must_use] fn roving_tabindex(self, orientation: crate::aria::Orientation) -> Self { self.add_roving_tabindex(orientation); self }
+
+	/// whether the mouse is over this element
+	///
+	/// the `Mutable` and its listeners are created on first call, shared by all callers and removed with the element
+	fn hover_signal(&self) -> MutableSignal<bool> {
+		Tracked::<Hover, bool>::get_or_init(self.as_entity(), || false, |target, hovered| vec![
+			target.on_mouse_enter({ let hovered = hovered.clone(); move |_| hovered.set_neq(true) }),
+			target.on_mouse_leave({ let hovered = hovered.clone(); move |_| hovered.set_neq(false) }),
+		]).signal()
+	}
+
+	/// whether this element has focus, see `hover_signal`
+	fn focus_signal(&self) -> MutableSignal<bool> {
+		let initial = || active_element().is_some_and(|x| x == *self.get_cmp::<web_sys::Element>());
+		Tracked::<Focus, bool>::get_or_init(self.as_entity(), initial, |target, focused| vec![
+			target.on_focus({ let focused = focused.clone(); move |_| focused.set_neq(true) }),
+			target.on_blur({ let focused = focused.clone(); move |_| focused.set_neq(false) }),
+		]).signal()
+	}
+
+	/// whether this element or any of its descendants has focus, see `hover_signal`
+	fn focus_within_signal(&self) -> MutableSignal<bool> {
+		let initial = || active_element().is_some_and(|x| self.get_cmp::<web_sys::Node>().contains(Some(&x)));
+		Tracked::<FocusWithin, bool>::get_or_init(self.as_entity(), initial, |target, focused| vec![
+			target.on_focus_in({ let focused = focused.clone(); move |_| focused.set_neq(true) }),
+			target.on_focus_out({
+				let focused = focused.clone();
+				// focus moving between descendants doesn't count as leaving
+				move |e| {
+					let node = e.current_target().and_then(|x| x.dyn_into::<web_sys::Node>().ok());
+					focused.set_neq(e.related_target().and_then(|x| x.dyn_into::<web_sys::Node>().ok()).is_some_and(|x| node.is_some_and(|node| node.contains(Some(&x)))));
+				}
+			}),
+		]).signal()
+	}
+
+	/// whether a pointer is pressed down on this element, similar to `:active`, see `hover_signal`
+	fn pressed_signal(&self) -> MutableSignal<bool> {
+		Tracked::<Pressed, bool>::get_or_init(self.as_entity(), || false, |target, pressed| vec![
+			target.on_pointer_down({ let pressed = pressed.clone(); move |_| pressed.set_neq(true) }),
+			target.on_pointer_up({ let pressed = pressed.clone(); move |_| pressed.set_neq(false) }),
+			target.on_pointer_cancel({ let pressed = pressed.clone(); move |_| pressed.set_neq(false) }),
+			target.on_pointer_leave({ let pressed = pressed.clone(); move |_| pressed.set_neq(false) }),
+		]).signal()
+	}
+}
+
+pub(crate) fn active_element() -> Option<web_sys::Element> {
+	if crate::ssr::ENABLED { return None; }
+	web_sys::window().expect("no window").document().expect("no document").active_element()
 }
 
 impl<T: AsElement> AsElement for &T {}
 impl<T: AsElement> AsElement for &mut T {}
+
+// anchors render as comments, which only get in the way of checking the order of children
+#[cfg(test)]
+fn render_without_comments(root: impl AsElement) -> String {
+	let mut html = crate::ssr::render_html(root);
+	while let Some(start) = html.find("<!--") {
+		let end = html[start..].find("-->").map_or(html.len(), |x| start + x + 3);
+		html.replace_range(start..end, "");
+	}
+	html
+}
+
+#[test]
+fn children_signal_vec_diffs() {
+	use crate::create as e;
+	use futures_signals::signal_vec::MutableVec;
+
+	let items = MutableVec::new_with_values(vec![1, 2, 3]);
+	let root = e::div().child(e::i()).children_signal_vec(items.signal_vec(), |x| e::span().text(x.to_string())).child(e::b());
+	assert_eq!(render_without_comments(root), "<div><i></i><span>1</span><span>2</span><span>3</span><b></b></div>");
+
+	items.lock_mut().insert(0, 0);
+	items.lock_mut().remove(2);
+	items.lock_mut().move_from_to(0, 2);
+	items.lock_mut().set(0, 9);
+	items.lock_mut().push(4);
+	assert_eq!(render_without_comments(root), "<div><i></i><span>9</span><span>3</span><span>0</span><span>4</span><b></b></div>");
+
+	items.lock_mut().pop();
+	items.lock_mut().replace(vec![7, 8]);
+	assert_eq!(render_without_comments(root), "<div><i></i><span>7</span><span>8</span><b></b></div>");
+	items.lock_mut().clear();
+	assert_eq!(render_without_comments(root), "<div><i></i><b></b></div>");
+	// `i`, `b` and the anchor
+	assert_eq!(root.get_cmp::<Children>().len(), 3);
+}
+
+#[test]
+fn children_signal_vec_keyed_diffs() {
+	use crate::create as e;
+	use futures_signals::signal_vec::MutableVec;
+
+	let items = MutableVec::new_with_values(vec![(1, "a"), (1, "b"), (2, "c"), (3, "d")]);
+	let root = e::div().children_signal_vec_keyed(items.signal_vec(), |x| x.0, |x| e::span().text(x.1));
+	assert_eq!(render_without_comments(root), "<div><span>a</span><span>b</span><span>c</span><span>d</span></div>");
+
+	// reused items keep what they were built with, items with the same key are reused in order
+	items.lock_mut().replace(vec![(3, "x"), (2, "y"), (1, "z"), (4, "w"), (1, "v")]);
+	assert_eq!(render_without_comments(root), "<div><span>d</span><span>c</span><span>a</span><span>w</span><span>b</span></div>");
+	items.lock_mut().replace(vec![(1, "z")]);
+	assert_eq!(render_without_comments(root), "<div><span>a</span></div>");
+	assert_eq!(root.get_cmp::<Children>().len(), 2);
+
+	items.lock_mut().set(0, (1, "y"));
+	items.lock_mut().push((5, "e"));
+	items.lock_mut().set(1, (6, "f"));
+	items.lock_mut().insert(0, (7, "g"));
+	items.lock_mut().move_from_to(2, 0);
+	assert_eq!(render_without_comments(root), "<div><span>f</span><span>g</span><span>a</span></div>");
+}
+
+#[test]
+fn children_signal_map_diffs() {
+	use crate::create as e;
+	use futures_signals::signal_map::MutableBTreeMap;
+
+	let items = MutableBTreeMap::new();
+	items.lock_mut().insert(2, "b");
+	let root = e::div().child(e::i()).children_signal_map(items.signal_map(), |k, v| e::span().text(format!("{k}{v}"))).child(e::b());
+	assert_eq!(render_without_comments(root), "<div><i></i><span>2b</span><b></b></div>");
+
+	items.lock_mut().insert(3, "c");
+	items.lock_mut().insert(1, "a");
+	items.lock_mut().insert(2, "x");
+	assert_eq!(render_without_comments(root), "<div><i></i><span>1a</span><span>2x</span><span>3c</span><b></b></div>");
+
+	items.lock_mut().remove(&1);
+	assert_eq!(render_without_comments(root), "<div><i></i><span>2x</span><span>3c</span><b></b></div>");
+	items.lock_mut().clear();
+	assert_eq!(render_without_comments(root), "<div><i></i><b></b></div>");
+	assert_eq!(root.get_cmp::<Children>().len(), 3);
+}
+
+#[test]
+fn child_signal_option_diffs() {
+	use crate::create as e;
+	use futures_signals::signal::{Mutable, SignalExt};
+
+	let shown = Mutable::new(None::<&str>);
+	let root = e::div()
+		.child(e::i())
+		.child_signal_option(shown.signal().map(|x| x.map(|x| e::span().text(x))))
+		.child_signal(shown.signal().map(|x| if x.is_some() { e::p().as_element() } else { e::br().as_element() }))
+		.child(e::b());
+	assert_eq!(render_without_comments(root), "<div><i></i><br><b></b></div>");
+
+	shown.set(Some("a"));
+	assert_eq!(render_without_comments(root), "<div><i></i><span>a</span><p></p><b></b></div>");
+	shown.set(Some("b"));
+	assert_eq!(render_without_comments(root), "<div><i></i><span>b</span><p></p><b></b></div>");
+	shown.set(None);
+	assert_eq!(render_without_comments(root), "<div><i></i><br><b></b></div>");
+}
+
+#[test]
+fn fragments_flatten() {
+	use crate::create as e;
+	use futures_signals::signal_vec::MutableVec;
+
+	let pair = |x: u32| e::fragment().child(e::dt().text(x.to_string())).child(e::dd());
+	let items = MutableVec::new_with_values(vec![1, 2]);
+	let root = e::dl().children_signal_vec(items.signal_vec(), pair).child(e::div());
+	assert_eq!(render_without_comments(root), "<dl><dt>1</dt><dd></dd><dt>2</dt><dd></dd><div></div></dl>");
+
+	items.lock_mut().move_from_to(1, 0);
+	items.lock_mut().set(1, 3);
+	assert_eq!(render_without_comments(root), "<dl><dt>2</dt><dd></dd><dt>3</dt><dd></dd><div></div></dl>");
+
+	let fragment = pair(4);
+	let root = e::dl().child(e::div()).child(fragment).child(e::b());
+	assert_eq!(render_without_comments(root), "<dl><div></div><dt>4</dt><dd></dd><b></b></dl>");
+	fragment.replace_with(pair(5));
+	assert_eq!(render_without_comments(root), "<dl><div></div><dt>5</dt><dd></dd><b></b></dl>");
+}
+
+#[test]
+fn shadow_root_children() {
+	use crate::create as e;
+
+	crate::style_storage::clear_rendered_styles();
+	let host = e::div().child(e::b());
+	let root = host.attach_shadow(web_sys::ShadowRootMode::Open);
+	let span = e::span().text("shadow");
+	root.add_child(span);
+	assert_eq!(render_without_comments(host), r#"<div><template shadowrootmode="open"><span>shadow</span></template><b></b></div>"#);
+
+	span.leave_parent();
+	let i = e::i().class(css::class!(css::opacity::val(0.5)));
+	root.add_child(i);
+	let (styles, class) = (crate::style_storage::rendered_styles(), i.get_attr("class").unwrap());
+	assert!(styles.contains("opacity:0.5"));
+	assert_eq!(render_without_comments(host), format!(r#"<div><template shadowrootmode="open">{styles}<i class="{class}"></i></template><b></b></div>"#));
+	assert!(span.try_get_cmp::<Parent>().is_none());
+}
+
+#[test]
+fn tracked_signals_on_the_server() {
+	use crate::create as e;
+
+	let input = e::input().value_attr("a");
+	let root = e::div()
+		.bool_attr_signal("data-hovered", input.hover_signal())
+		.bool_attr_signal("data-focused", input.focus_signal())
+		.child(input)
+		.child(e::span().text_signal(input.value_signal()));
+	crate::ssr::run_pending();
+	assert_eq!(render_without_comments(root), r#"<div><input value="a"><span>a</span></div>"#);
+}
+
+#[test]
+fn fragments_ignore_element_methods() {
+	use crate::create as e;
+
+	crate::style_storage::clear_rendered_styles();
+	let fragment = e::fragment()
+		.class(css::class!(css::opacity::val(0.5)))
+		.attr("data-x", "1")
+		.style(css::opacity::val(0.5))
+		.mark::<Classes>()
+		.text("text")
+		.child(e::span());
+	fragment.set_prop("hidden", true);
+	fragment.blur();
+	assert_eq!(fragment.get_attr("data-x"), None);
+
+	let host = e::div();
+	let root = host.attach_shadow(web_sys::ShadowRootMode::Open).class(css::class!(css::opacity::val(0.5))).child(e::b());
+	assert_eq!(root.get_attr("class"), None);
+	assert_eq!(render_without_comments(e::div().child(fragment).child(host)), r#"<div><span></span><div><template shadowrootmode="open"><b></b></template></div></div>"#);
+}
+
+#[test]
+fn portal_children() {
+	use crate::create as e;
+
+	let page = e::div().child(e::b());
+	let modal = e::span().text("modal");
+	page.add_portal_child(PortalTarget::Body, modal);
+	assert_eq!(render_without_comments(page), "<div><b></b></div>");
+	assert_eq!(page.get_cmp::<Children>().0, [page.get_cmp::<Children>().0[0], modal.as_entity()]);
+	assert_eq!(modal.parent(), page.as_element());
+	assert!(modal.as_element().is_portal());
+
+	let replacement = e::i();
+	modal.replace_with(replacement);
+	assert!(modal.is_dead());
+	assert!(replacement.as_element().is_portal() && replacement.parent() == page.as_element());
+	assert_eq!(render_without_comments(page), "<div><b></b></div>");
+
+	replacement.leave_parent();
+	assert!(!replacement.as_element().is_portal() && replacement.try_get_cmp::<Parent>().is_none());
+	assert_eq!(page.get_cmp::<Children>().len(), 1);
+
+	let toast = e::p();
+	page.add_portal_child(PortalTarget::Body, toast);
+	page.remove();
+	assert!(toast.is_dead());
+	replacement.remove();
+}
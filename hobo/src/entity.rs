@@ -13,6 +13,21 @@ pub struct FutureHandlesCollection(pub Vec<discard::DiscardOnDrop<futures_signal
 
 impl Entity {
 	pub(crate) fn root() -> Self { Self(0) }
+
+	/// Find the entity a DOM node belongs to, e.g. for event targets or `query_selector` results
+	///
+	/// Returns `None` for nodes that weren't created by hobo or whose entity has been removed.
+	pub fn from_node(node: &web_sys::Node) -> Option<Self> {
+		let entity = Self(js_sys::Reflect::get(node, &JsValue::from_str(web_str::__hobo_entity())).ok()?.as_f64()? as u64);
+
+		// the node could have outlived its entity
+		entity.try_get_cmp::<web_sys::Node>().is_some_and(|x| *x == *node).then_some(entity)
+	}
+
+	// store the entity on the node itself, so that `from_node` doesn't need any lookup tables
+	pub(crate) fn link_node(self, node: &web_sys::Node) {
+		js_sys::Reflect::set(node, &JsValue::from_str(web_str::__hobo_entity()), &JsValue::from(self.0 as f64)).expect("can't link node to entity");
+	}
 }
 
 pub trait AsEntity {
@@ -117,7 +132,7 @@ pub trait AsEntity {
 
 	fn spawn(&self, f: impl std::future::Future<Output = ()> + 'static) {
 		let (handle, fut) = futures_signals::cancelable_future(f, Default::default);
-		wasm_bindgen_futures::spawn_local(fut);
+		crate::signals_ext::spawn(fut);
 		self.get_cmp_mut_or_default::<FutureHandlesCollection>().0.push(handle);
 	}
 
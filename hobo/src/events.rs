@@ -5,7 +5,6 @@ use std::{
 	collections::HashMap,
 	rc::Rc,
 };
-use once_cell::sync::Lazy;
 
 slotmap::new_key_type! {struct SubKey;}
 
@@ -16,7 +15,11 @@ struct Events {
 	subscribers: RefCell<HashMap<TypeId, DenseSlotMap<SubKey, SubFn>>>,
 }
 
-static EVENTS: Lazy<Events> = Lazy::new(Default::default);
+thread_local! {
+	static THREAD_EVENTS: &'static Events = Box::leak(Box::default());
+}
+
+static EVENTS: crate::world::PerThread<Events> = crate::world::PerThread::new(&THREAD_EVENTS);
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct SubInfo {
@@ -24,11 +27,6 @@ struct SubInfo {
 	typeid: TypeId,
 }
 
-// This is only permissible because JS/WASM is single-threaded
-// would have to be rethunked if/wheen threading arrives via std::thread
-unsafe impl Send for Events {}
-unsafe impl Sync for Events {}
-
 // TODO: nested subscribtions etc?
 impl Events {
 	fn fire<E: Any>(&self, e: &E) {
@@ -0,0 +1,146 @@
+//! focus traps and roving tabindex
+//!
+//! Only elements that are entities are considered, e.g. links inside of markup from `set_html` aren't.
+
+use crate::{prelude::*, aria::Orientation, element::active_element};
+use std::cmp::Ordering;
+
+const NATIVELY_FOCUSABLE: &[&str] = &["button", "select", "textarea", "iframe", "summary"];
+
+// focusable at all, with or without being in the tab order
+fn can_focus(element: &web_sys::Element) -> bool {
+	if element.has_attribute("disabled") || element.has_attribute("hidden") { return false; }
+	if element.has_attribute("tabindex") { return true; }
+
+	let tag = element.tag_name().to_ascii_lowercase();
+	match tag.as_str() {
+		"input" => element.get_attribute("type").as_deref() != Some("hidden"),
+		"a" | "area" => element.has_attribute("href"),
+		tag => NATIVELY_FOCUSABLE.contains(&tag) || element.get_attribute("contenteditable").is_some_and(|x| x != "false"),
+	}
+}
+
+fn tabbable(element: &web_sys::Element) -> bool {
+	can_focus(element) && element.get_attribute("tabindex").and_then(|x| x.trim().parse::<i32>().ok()).is_none_or(|x| x >= 0)
+}
+
+// `Children::descendants` isn't in document order
+fn descendant_elements(entity: Entity, filter: fn(&web_sys::Element) -> bool) -> Vec<web_sys::Element> {
+	let mut res = Children::descendants(entity).into_iter()
+		.filter_map(|x| x.try_get_cmp::<web_sys::Element>().map(|x| x.clone()))
+		.filter(filter)
+		.collect::<Vec<_>>();
+	res.sort_by(|a, b| {
+		if a == b { return Ordering::Equal; }
+		if a.compare_document_position(b) & web_sys::Node::DOCUMENT_POSITION_FOLLOWING != 0 { Ordering::Less } else { Ordering::Greater }
+	});
+	res
+}
+
+fn focus_element(element: &web_sys::Element) { element.unchecked_ref::<web_sys::HtmlElement>().focus().ok(); }
+
+// focusing a node that isn't attached yet does nothing, so it's tried again once the current task is done
+pub(crate) fn focus_when_attached(entity: Entity) {
+	let element = entity.get_cmp::<web_sys::Element>().clone();
+	if element.is_connected() { focus_element(&element); return; }
+	wasm_bindgen_futures::spawn_local(async move {
+		if entity.is_dead() { return; }
+		focus_element(&element);
+	});
+}
+
+/// Keeps Tab and Shift+Tab within the element's descendants, focus goes back where it was when it's removed
+///
+/// Added with `AsElement::focus_trap`.
+pub struct FocusTrap {
+	previous: Option<web_sys::Element>,
+}
+
+impl Drop for FocusTrap {
+	fn drop(&mut self) {
+		if let Some(previous) = self.previous.take().filter(|x| x.is_connected()) { focus_element(&previous); }
+	}
+}
+
+pub(crate) fn add_focus_trap(element: Element) {
+	if element.is_dead() { log::warn!("add_focus_trap dead entity {:?}", element.as_entity()); return; }
+	if crate::ssr::ENABLED { return; }
+	let entity = element.as_entity();
+
+	element.add_component(FocusTrap { previous: active_element() });
+	element.add_on_key_down(move |e| {
+		if e.key() != "Tab" { return; }
+		let focusables = descendant_elements(entity, tabbable);
+		let (Some(first), Some(last)) = (focusables.first(), focusables.last()) else { e.prevent_default(); return; };
+
+		let active = active_element();
+		let inside = active.as_ref().is_some_and(|x| focusables.contains(x));
+		let target = match (e.shift_key(), active.as_ref()) {
+			(true, Some(active)) if inside && active != first => return,
+			(false, Some(active)) if inside && active != last => return,
+			(true, _) => last,
+			(false, _) => first,
+		};
+		e.prevent_default();
+		focus_element(target);
+	});
+
+	// focus moves into the trap, unless it's already there
+	wasm_bindgen_futures::spawn_local(async move {
+		if entity.is_dead() { return; }
+		let node = entity.get_cmp::<web_sys::Node>().clone();
+		if active_element().is_some_and(|x| node.contains(Some(&x))) { return; }
+		if let Some(first) = descendant_elements(entity, tabbable).first() { focus_element(first); }
+	});
+}
+
+// only the current item is in the tab order
+fn sync_roving(entity: Entity, current: Option<&web_sys::Element>) -> Vec<web_sys::Element> {
+	let items = descendant_elements(entity, can_focus);
+	let current = current.filter(|x| items.contains(x)).cloned()
+		.or_else(|| items.iter().find(|x| x.get_attribute("tabindex").as_deref() == Some("0")).cloned())
+		.or_else(|| items.first().cloned());
+	for item in &items {
+		item.set_attribute("tabindex", if Some(item) == current.as_ref() { "0" } else { "-1" }).expect("can't set tabindex");
+	}
+	items
+}
+
+pub(crate) fn add_roving_tabindex(element: Element, orientation: Orientation) {
+	if element.is_dead() { log::warn!("add_roving_tabindex dead entity {:?}", element.as_entity()); return; }
+	if crate::ssr::ENABLED { return; }
+	let entity = element.as_entity();
+
+	element.add_on_focus_in(move |e| {
+		let target = e.target().and_then(|x| x.dyn_into::<web_sys::Element>().ok());
+		sync_roving(entity, target.as_ref());
+	});
+	element.add_on_key_down(move |e| {
+		let (previous, next) = match orientation {
+			Orientation::Horizontal => ("ArrowLeft", "ArrowRight"),
+			Orientation::Vertical => ("ArrowUp", "ArrowDown"),
+		};
+		let key = e.key();
+		if ![previous, next, "Home", "End"].contains(&key.as_str()) { return; }
+
+		let active = active_element();
+		let items = sync_roving(entity, active.as_ref());
+		if items.is_empty() { return; }
+		let position = active.and_then(|active| items.iter().position(|x| *x == active)).unwrap_or(0);
+		let target = match key.as_str() {
+			"Home" => 0,
+			"End" => items.len() - 1,
+			x if x == previous => position.checked_sub(1).unwrap_or(items.len() - 1),
+			_ => (position + 1) % items.len(),
+		};
+		e.prevent_default();
+		sync_roving(entity, Some(&items[target]));
+		focus_element(&items[target]);
+	});
+
+	// items are usually added right after
+	wasm_bindgen_futures::spawn_local(async move {
+		if entity.is_dead() { return; }
+		sync_roving(entity, active_element().as_ref());
+	});
+}
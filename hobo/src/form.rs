@@ -0,0 +1,245 @@
+//! typed forms
+//!
+//! A struct with `#[derive(FormModel)]` is read from the named controls of a `Form`,
+//! the same way the browser would submit them, and every field can be validated:
+//!
+//! ```ignore
+//! #[derive(FormModel)]
+//! struct SignUp {
+//!     #[form(required, min_length = 3, max_length = 20, pattern = "[a-z0-9_]+")]
+//!     username: String,
+//!     #[form(name = "years", validate = adult)]
+//!     age: u32,
+//!     newsletter: bool,
+//!     referrer: Option<String>,
+//! }
+//!
+//! fn adult(x: &u32) -> Result<(), String> { if *x >= 18 { Ok(()) } else { Err("too young".to_owned()) } }
+//!
+//! let form = e::form()
+//!     .child(e::input().name("username"))
+//!     .child(e::input().type_number().name("years"))
+//!     .child(e::input().type_checkbox().name("newsletter"))
+//!     .on_submit_model(|x: SignUp| sign_up(x));
+//! form.add_child(e::div().text_signal(form.errors_signal("username").map(|x| x.first().map(ToString::to_string).unwrap_or_default())));
+//! ```
+
+use crate::prelude::*;
+use futures_signals::signal::{Mutable, MutableSignalCloned};
+use std::{cell::Cell, collections::{BTreeMap, HashMap}, rc::Rc};
+pub use hobo_derive::FormModel;
+
+/// Something that can be read from the controls of a `Form`, usually derived
+pub trait FormModel: Sized {
+	/// `get` returns the value of the control with a name, like `FormData::get` but `None` for files
+	fn from_values(get: &dyn Fn(&str) -> Option<String>) -> Result<Self, FieldErrors>;
+	fn from_form_data(data: &web_sys::FormData) -> Result<Self, FieldErrors> { Self::from_values(&|name| data.get(name).as_string()) }
+}
+
+/// Why a field didn't pass validation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldError {
+	Required,
+	MinLength(usize),
+	MaxLength(usize),
+	Pattern,
+	/// the value couldn't be converted to the type of the field
+	Invalid,
+	Custom(String),
+}
+
+impl std::fmt::Display for FieldError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Self::Required => write!(f, "required"),
+			Self::MinLength(x) => write!(f, "must be at least {x} characters long"),
+			Self::MaxLength(x) => write!(f, "must be at most {x} characters long"),
+			Self::Pattern => write!(f, "doesn't match the expected format"),
+			Self::Invalid => write!(f, "invalid value"),
+			Self::Custom(x) => write!(f, "{x}"),
+		}
+	}
+}
+
+/// Errors of every field that didn't pass validation, by control name
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FieldErrors(pub BTreeMap<&'static str, Vec<FieldError>>);
+
+impl FieldErrors {
+	pub fn get(&self, name: &str) -> &[FieldError] { self.0.get(name).map_or(&[], Vec::as_slice) }
+	pub fn is_empty(&self) -> bool { self.0.is_empty() }
+}
+
+/// A field type of a `FormModel`
+pub trait FromFormValue: Sized {
+	/// `value` is `None` if the form has nothing for the field, e.g. an unchecked checkbox
+	fn from_form_value(value: Option<&str>) -> Result<Self, FieldError>;
+}
+
+impl FromFormValue for String {
+	fn from_form_value(value: Option<&str>) -> Result<Self, FieldError> { Ok(value.unwrap_or_default().to_owned()) }
+}
+
+/// whether a checkbox is checked
+impl FromFormValue for bool {
+	fn from_form_value(value: Option<&str>) -> Result<Self, FieldError> { Ok(value.is_some()) }
+}
+
+/// `None` if the value is missing or empty
+impl<T: FromFormValue> FromFormValue for Option<T> {
+	fn from_form_value(value: Option<&str>) -> Result<Self, FieldError> {
+		match value {
+			None | Some("") => Ok(None),
+			x => T::from_form_value(x).map(Some),
+		}
+	}
+}
+
+macro_rules! from_str_form_value {
+	($($t:ty),*) => {$(
+		impl FromFormValue for $t {
+			fn from_form_value(value: Option<&str>) -> Result<Self, FieldError> {
+				match value {
+					None | Some("") => Err(FieldError::Required),
+					Some(x) => x.trim().parse().map_err(|_| FieldError::Invalid),
+				}
+			}
+		}
+	)*};
+}
+
+from_str_form_value!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize, f32, f64, char);
+
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Validator {
+	Required,
+	MinLength(usize),
+	MaxLength(usize),
+	/// same syntax as the `pattern` attribute, has to match the whole value, only checked when compiled to wasm
+	Pattern(&'static str),
+}
+
+// there are no regular expressions without js, so on the server patterns are left to the browser and to `validate`
+fn matches_pattern(value: &str, pattern: &str) -> bool {
+	if crate::ssr::ENABLED { return true; }
+	js_sys::RegExp::new(&format!("^(?:{pattern})$"), "u").test(value)
+}
+
+// empty values are only checked by `Required`, same as with the constraint validation attributes
+#[doc(hidden)]
+pub fn read_field<T: FromFormValue>(get: &dyn Fn(&str) -> Option<String>, name: &'static str, validators: &[Validator], errors: &mut FieldErrors) -> Option<T> {
+	let value = get(name);
+	let mut field_errors = Vec::new();
+	match value.as_deref() {
+		None | Some("") => if validators.contains(&Validator::Required) { field_errors.push(FieldError::Required); },
+		Some(x) => for validator in validators {
+			match *validator {
+				Validator::MinLength(len) if x.chars().count() < len => field_errors.push(FieldError::MinLength(len)),
+				Validator::MaxLength(len) if x.chars().count() > len => field_errors.push(FieldError::MaxLength(len)),
+				Validator::Pattern(pattern) if !matches_pattern(x, pattern) => field_errors.push(FieldError::Pattern),
+				_ => {},
+			}
+		},
+	}
+
+	let res = if field_errors.is_empty() {
+		match T::from_form_value(value.as_deref()) {
+			Ok(x) => Some(x),
+			Err(e) => { field_errors.push(e); None },
+		}
+	} else { None };
+	if !field_errors.is_empty() { errors.0.insert(name, field_errors); }
+	res
+}
+
+#[doc(hidden)]
+pub fn validate_field<T>(value: Option<&T>, name: &'static str, f: impl FnOnce(&T) -> Result<(), String>, errors: &mut FieldErrors) {
+	if let Some(Err(e)) = value.map(f) { errors.0.entry(name).or_default().push(FieldError::Custom(e)); }
+}
+
+#[derive(Default)]
+struct FormErrors(HashMap<String, Mutable<Vec<FieldError>>>);
+
+impl crate::create::Form {
+	/// Read and validate the controls, also updates every `errors_signal`
+	pub fn read<T: FormModel>(&self) -> Result<T, FieldErrors> {
+		let data = web_sys::FormData::new_with_form(&self.get_cmp::<web_sys::HtmlFormElement>()).expect("can't create FormData");
+		let res = T::from_form_data(&data);
+		if let Some(form_errors) = self.try_get_cmp::<FormErrors>() {
+			let errors = res.as_ref().err();
+			for (name, field_errors) in &form_errors.0 {
+				field_errors.set_neq(errors.map(|x| x.get(name).to_vec()).unwrap_or_default());
+			}
+		}
+		res
+	}
+
+	/// Errors of the control named `name` as of the last `read`, empty until the form is read for the first time
+	pub fn errors_signal(&self, name: &str) -> MutableSignalCloned<Vec<FieldError>> {
+		if self.is_dead() { log::warn!("errors_signal dead {:?}", self.as_entity()); return Mutable::default().signal_cloned(); }
+		self.get_cmp_mut_or_default::<FormErrors>().0.entry(name.to_owned()).or_default().signal_cloned()
+	}
+
+	/// Prevent the default submission, then call `f` if the form reads without errors
+	///
+	/// After the first submission the form is read again on every `input`, so that `errors_signal` follows the fixes.
+	pub fn add_on_submit_model<T: FormModel + 'static>(&self, mut f: impl FnMut(T) + 'static) {
+		let this = *self;
+		let submitted = Rc::new(Cell::new(false));
+		self.add_on_submit({
+			let submitted = Rc::clone(&submitted);
+			move |e| {
+				e.prevent_default();
+				submitted.set(true);
+				if let Ok(x) = this.read::<T>() { f(x); }
+			}
+		});
+		// `input` bubbles up from the controls
+		self.add_on_input(move |_| if submitted.get() { this.read::<T>().ok(); });
+	}
+	#[must_use] pub fn on_submit_model<T: FormModel + 'static>(self, f: impl FnMut(T) + 'static) -> Self { self.add_on_submit_model(f); self }
+}
+
+#[cfg(test)]
+#[allow(clippy::trivially_copy_pass_by_ref, reason = "validators take the value by reference")]
+fn adult(x: &u32) -> Result<(), String> { if *x >= 18 { Ok(()) } else { Err("too young".to_owned()) } }
+
+#[cfg(test)]
+#[derive(FormModel, Debug, PartialEq, Eq)]
+struct SignUp {
+	#[form(required, min_length = 3, max_length = 5, pattern = "[a-z]+")]
+	username: String,
+	#[form(name = "years", validate = adult, validate = |x| if *x < 150 { Ok(()) } else { Err("too old".to_owned()) })]
+	age: u32,
+	newsletter: bool,
+	referrer: Option<String>,
+}
+
+#[cfg(test)]
+fn read_sign_up(values: &[(&str, &str)]) -> Result<SignUp, FieldErrors> {
+	SignUp::from_values(&|name| values.iter().find(|(k, _)| *k == name).map(|(_, v)| (*v).to_owned()))
+}
+
+#[test]
+fn reads_models() {
+	assert_eq!(read_sign_up(&[("username", "abcd"), ("years", "20"), ("newsletter", "on")]), Ok(SignUp { username: "abcd".to_owned(), age: 20, newsletter: true, referrer: None }));
+	assert_eq!(read_sign_up(&[("username", "abc"), ("years", "20"), ("referrer", "x")]).map(|x| x.referrer), Ok(Some("x".to_owned())));
+
+	let errors = read_sign_up(&[("years", "abc")]).unwrap_err();
+	assert_eq!(errors.get("username"), [FieldError::Required]);
+	assert_eq!(errors.get("years"), [FieldError::Invalid]);
+	assert_eq!(read_sign_up(&[("username", "ab"), ("years", "17")]).unwrap_err().get("username"), [FieldError::MinLength(3)]);
+	assert_eq!(read_sign_up(&[("username", "abcdef"), ("years", "17")]).unwrap_err().get("username"), [FieldError::MaxLength(5)]);
+	assert_eq!(read_sign_up(&[("username", "abc"), ("years", "17")]).unwrap_err().get("years"), [FieldError::Custom("too young".to_owned())]);
+	assert_eq!(read_sign_up(&[("username", "abc"), ("years", "200")]).unwrap_err().get("years"), [FieldError::Custom("too old".to_owned())]);
+	if crate::ssr::ENABLED { assert!(read_sign_up(&[("username", "abc1"), ("years", "20")]).is_ok()); }
+}
+
+// patterns are matched by the browser's regular expressions
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn reads_patterns() {
+	assert_eq!(read_sign_up(&[("username", "abc1"), ("years", "20")]).unwrap_err().get("username"), [FieldError::Pattern]);
+	assert!(read_sign_up(&[("username", "abc"), ("years", "20")]).is_ok());
+}
@@ -0,0 +1,251 @@
+//! attaching to server-rendered markup
+//!
+//! `hydrate` builds elements as usual, then walks them alongside the existing nodes and adopts the existing ones,
+//! so the page doesn't get recreated and e.g. focus, scroll position or loaded images are kept.
+//! Anything that doesn't match what the server rendered is replaced with what the client built.
+
+use crate::{prelude::*, dom_events::EventHandler};
+use std::{future::Future, sync::atomic::{AtomicBool, Ordering}, task::{Context, Waker}};
+
+static HYDRATING: AtomicBool = AtomicBool::new(false);
+
+pub(crate) fn is_hydrating() -> bool { HYDRATING.load(Ordering::Relaxed) }
+
+// moves an element's `web_sys` components to another node, knows the specific type of the element
+pub(crate) struct Hydratable(fn(Entity, &web_sys::Element));
+
+fn replace_cmp<C: JsCast + 'static>(entity: Entity, node: &web_sys::Element) {
+	if let Some(x) = WORLD.storage_mut::<C>().get_mut(entity) { *x = node.clone().unchecked_into(); }
+}
+
+fn adopt_html<T: JsCast + 'static>(entity: Entity, node: &web_sys::Element) {
+	replace_cmp::<web_sys::HtmlElement>(entity, node);
+	replace_cmp::<T>(entity, node);
+}
+
+fn adopt_svg<T: JsCast + 'static>(entity: Entity, node: &web_sys::Element) {
+	replace_cmp::<web_sys::SvgElement>(entity, node);
+	replace_cmp::<T>(entity, node);
+}
+
+pub(crate) fn html_element<T: JsCast + 'static>(entity: Entity) {
+	if is_hydrating() { WORLD.storage_mut::<Hydratable>().add(entity, Hydratable(adopt_html::<T>)); }
+}
+
+pub(crate) fn svg_element<T: JsCast + 'static>(entity: Entity) {
+	if is_hydrating() { WORLD.storage_mut::<Hydratable>().add(entity, Hydratable(adopt_svg::<T>)); }
+}
+
+// signals are polled right away so that their children exist by the time nodes are matched
+pub(crate) fn spawn(fut: impl Future<Output = ()> + 'static) {
+	let mut fut = Box::pin(fut);
+	if fut.as_mut().poll(&mut Context::from_waker(Waker::noop())).is_pending() {
+		wasm_bindgen_futures::spawn_local(fut);
+	}
+}
+
+// entities whose nodes are placed directly into the parent's node, portals and shadow roots are attached elsewhere
+fn dom_children(entity: Entity) -> Vec<Entity> {
+	let children = entity.try_get_cmp::<Children>().map(|x| x.0.clone()).unwrap_or_default();
+	children.into_iter()
+		.filter(|&x| !crate::element::outside_parent_dom(x))
+		.flat_map(|x| if x.has_cmp::<crate::create::FragmentMarker>() { dom_children(x) } else { vec![x] })
+		.collect()
+}
+
+// text nodes come from `set_text` and aren't entities
+fn node_children(node: &web_sys::Node) -> Vec<web_sys::Node> {
+	let nodes = node.child_nodes();
+	(0..nodes.length()).filter_map(|i| nodes.get(i)).filter(|x| x.node_type() != web_sys::Node::TEXT_NODE).collect()
+}
+
+fn own_text(node: &web_sys::Node) -> String {
+	let nodes = node.child_nodes();
+	(0..nodes.length()).filter_map(|i| nodes.get(i)).filter(|x| x.node_type() == web_sys::Node::TEXT_NODE).filter_map(|x| x.text_content()).collect()
+}
+
+// * `data-entity` and `data-location` only exist in debug builds on the client
+// * class names of marks depend on the build, so `class` is taken from the client as is
+// * `value` and `checked` are rendered as attributes on the server, but the client only sets the properties
+const UNCOMPARED_ATTRIBUTES: &[&str] = &["data-entity", "data-location", "class", "value", "checked"];
+
+fn attributes(element: &web_sys::Element) -> Vec<(String, String)> {
+	let mut res = element.get_attribute_names().iter()
+		.filter_map(|x| x.as_string())
+		.filter(|x| !UNCOMPARED_ATTRIBUTES.contains(&x.as_str()))
+		.map(|x| { let value = element.get_attribute(&x).unwrap_or_default(); (x, value) })
+		.collect::<Vec<_>>();
+	res.sort();
+	res
+}
+
+fn mismatch(fresh: &web_sys::Node, existing: &web_sys::Node) -> Option<String> {
+	if fresh.node_name() != existing.node_name() {
+		return Some(format!("expected {}, found {}", fresh.node_name(), existing.node_name()));
+	}
+	if let (Some(fresh), Some(existing)) = (fresh.dyn_ref::<web_sys::Element>(), existing.dyn_ref::<web_sys::Element>()) {
+		let (fresh_attrs, existing_attrs) = (attributes(fresh), attributes(existing));
+		if fresh_attrs != existing_attrs {
+			return Some(format!("{} attributes differ, expected {:?}, found {:?}", fresh.node_name(), fresh_attrs, existing_attrs));
+		}
+	}
+	let (fresh_text, existing_text) = (own_text(fresh), own_text(existing));
+	if fresh_text != existing_text {
+		return Some(format!("{} text differs, expected {:?}, found {:?}", fresh.node_name(), fresh_text, existing_text));
+	}
+	None
+}
+
+// a shadow root can't be moved to another node, so the server-rendered one is simply replaced
+fn has_shadow_root(entity: Entity) -> bool {
+	entity.try_get_cmp::<Children>().is_some_and(|x| x.iter().any(AsEntity::has_cmp::<web_sys::ShadowRoot>))
+}
+
+fn adopt(entity: Entity, existing: &web_sys::Node) {
+	let fresh = entity.get_cmp::<web_sys::Node>().clone();
+	let mismatch = mismatch(&fresh, existing);
+	if mismatch.is_some() || has_shadow_root(entity) {
+		#[cfg(debug_assertions)] if let Some(reason) = mismatch { log::warn!("[hydrate] mismatch in entity {}: {reason}", entity.0); }
+		// what the client built wins, including everything inside
+		existing.parent_node().expect("hydrated node has no parent").replace_child(&fresh, existing).expect("can't replace mismatched node");
+		entity.remove_cmp::<Hydratable>();
+		return;
+	}
+
+	if let Some(element) = existing.dyn_ref::<web_sys::Element>() {
+		let fresh = fresh.unchecked_ref::<web_sys::Element>();
+		for attr in ["data-entity", "data-location", "class"] {
+			match fresh.get_attribute(attr) {
+				Some(value) => element.set_attribute(attr, &value).expect("can't set attribute"),
+				None => element.remove_attribute(attr).expect("can't remove attribute"),
+			}
+		}
+		let adopt_typed = entity.try_get_cmp::<Hydratable>().map(|x| x.0);
+		if let Some(adopt_typed) = adopt_typed { adopt_typed(entity, element); }
+		entity.remove_cmp::<Hydratable>();
+		replace_cmp::<web_sys::Element>(entity, element);
+		replace_cmp::<web_sys::EventTarget>(entity, element);
+	} else if let Some(comment) = existing.dyn_ref::<web_sys::Comment>() {
+		if let Some(x) = WORLD.storage_mut::<web_sys::Comment>().get_mut(entity) { *x = comment.clone(); }
+	}
+	if let Some(x) = WORLD.storage_mut::<web_sys::Node>().get_mut(entity) { *x = existing.clone(); }
+	entity.link_node(existing);
+
+	if let Some(mut handlers) = entity.try_get_cmp_mut::<Vec<EventHandler>>() {
+		for handler in handlers.iter_mut() { handler.retarget(existing); }
+	}
+
+	// markup from `set_html` has no entities to match, so the client's version is taken as is
+	if entity.has_cmp::<crate::element::InnerHtml>() {
+		let html = fresh.unchecked_ref::<web_sys::Element>().inner_html();
+		if let Some(element) = existing.dyn_ref::<web_sys::Element>().filter(|x| x.inner_html() != html) { element.set_inner_html(&html); }
+		return;
+	}
+	adopt_children(entity, existing);
+}
+
+fn adopt_children(entity: Entity, existing: &web_sys::Node) {
+	let entities = dom_children(entity);
+	let nodes = node_children(existing);
+	if entities.len() != nodes.len() {
+		#[cfg(debug_assertions)] log::warn!("[hydrate] entity {} has {} child nodes, found {}", entity.0, entities.len(), nodes.len());
+	}
+
+	for (i, &child) in entities.iter().enumerate() {
+		if let Some(node) = nodes.get(i) {
+			adopt(child, node);
+		} else {
+			// missing on the server, so it's inserted after the adopted ones
+			let fresh = child.get_cmp::<web_sys::Node>().clone();
+			existing.append_child(&fresh).expect("can't append child");
+			child.remove_cmp::<Hydratable>();
+		}
+	}
+	for extra in nodes.iter().skip(entities.len()) {
+		existing.remove_child(extra).expect("can't remove child");
+	}
+}
+
+/// Build an element with `root` and attach it to the server-rendered markup in `container` instead of creating new nodes
+///
+/// The markup of the element is expected to be the content of `container`, not counting the `<style>` elements
+/// rendered by `render_to_string`, which are reused wherever they are. Any other nodes in `container` are removed.
+pub fn hydrate<E: AsElement>(root: impl FnOnce() -> E, container: &web_sys::Element) -> E {
+	crate::aria::reset_ids();
+	HYDRATING.store(true, Ordering::Relaxed);
+	let root = root().allow_no_parent();
+	HYDRATING.store(false, Ordering::Relaxed);
+
+	let nodes = node_children(container).into_iter()
+		.filter(|x| !x.dyn_ref::<web_sys::Element>().is_some_and(|x| x.has_attribute("data-hobo-styles")))
+		.collect::<Vec<_>>();
+
+	let entities = dom_children_of_root(root.as_entity());
+	if entities.len() != nodes.len() {
+		#[cfg(debug_assertions)] log::warn!("[hydrate] root has {} nodes, found {} in the container", entities.len(), nodes.len());
+	}
+	for (i, &entity) in entities.iter().enumerate() {
+		if let Some(node) = nodes.get(i) {
+			adopt(entity, node);
+		} else {
+			container.append_child(&entity.get_cmp::<web_sys::Node>()).expect("can't append child");
+			entity.remove_cmp::<Hydratable>();
+		}
+	}
+	for extra in nodes.iter().skip(entities.len()) {
+		container.remove_child(extra).expect("can't remove child");
+	}
+
+	root
+}
+
+// the root itself might be a fragment
+fn dom_children_of_root(root: Entity) -> Vec<Entity> {
+	if root.has_cmp::<crate::create::FragmentMarker>() { dom_children(root) } else { vec![root] }
+}
+
+#[cfg(test)]
+fn counter_page(on_click: impl FnMut(web_sys::MouseEvent) + 'static) -> crate::create::Div {
+	use crate::create as e;
+
+	e::div().attr("id", "counter")
+		.child(e::button().attr("type", "button").text("increment").on_click(on_click))
+		.child(e::p().text("clicked"))
+}
+
+// what `render_to_string` renders for `counter_page`
+#[cfg(test)]
+const COUNTER_PAGE_HTML: &str = r#"<div id="counter"><button type="button">increment</button><p>clicked</p></div>"#;
+
+#[test]
+fn renders_counter_page() {
+	assert_eq!(crate::ssr::render_to_string(counter_page(|_| {})), COUNTER_PAGE_HTML);
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+#[wasm_bindgen_test::wasm_bindgen_test]
+fn adopts_server_rendered_nodes() {
+	use std::{cell::Cell, rc::Rc};
+
+	let document = web_sys::window().expect("no window").document().expect("no document");
+	let container = document.create_element("div").expect("can't create element");
+	container.set_inner_html(COUNTER_PAGE_HTML);
+	document.body().expect("no body").append_child(&container).expect("can't append child");
+	let existing_root = container.first_element_child().expect("no server-rendered root");
+	let existing_button = existing_root.first_element_child().expect("no server-rendered button");
+
+	let clicks = Rc::new(Cell::new(0));
+	let root = hydrate({ let clicks = Rc::clone(&clicks); move || counter_page(move |_| clicks.set(clicks.get() + 1)) }, &container);
+
+	// a mismatch would replace the nodes with the ones the client built
+	assert_eq!(container.child_element_count(), 1);
+	assert_eq!(*root.get_cmp::<web_sys::Element>(), existing_root);
+	let button = root.get_cmp::<Children>().0[0];
+	assert_eq!(*button.get_cmp::<web_sys::Element>(), existing_button);
+	assert_eq!(Entity::from_node(&existing_button), Some(button));
+
+	existing_button.unchecked_ref::<web_sys::HtmlElement>().click();
+	assert_eq!(clicks.get(), 1);
+	root.remove();
+	container.remove();
+}
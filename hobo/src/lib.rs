@@ -1,26 +1,40 @@
+pub mod a11y;
+pub mod animation;
+pub mod aria;
 /// methods to create HTML Elements as well as their types
 pub mod create;
+pub mod custom_element;
 pub mod dom_events;
 pub mod element;
 pub mod entity;
 /// simple way to fire and respond to arbitrarily-typed events
 pub mod events;
+pub mod focus;
+pub mod form;
 /// Parent and Child relations
 pub mod hierarchy;
+pub mod hydrate;
 pub mod prelude;
 pub mod query;
 mod racy_cell;
 /// resources are globally-accessible components
 pub mod resource;
+pub mod router;
+pub mod sanitize;
 pub mod signals_ext;
+pub mod ssr;
 mod storage;
 mod style_storage;
+mod transition;
+pub mod virtual_list;
 pub mod web_str;
+
 mod world;
 
 pub use web_sys;
 use crate::prelude::*;
 #[doc(hidden)] pub use discard;
+pub use custom_element::define_custom_element;
 pub use element::{AsElement, Element};
 pub use entity::AsEntity;
 pub use futures_signals::{self, signal, signal_map, signal_vec};
@@ -28,11 +42,12 @@ pub use hobo_css as css;
 use owning_ref::{OwningRef, OwningRefMut};
 #[doc(hidden)] pub use paste;
 pub use prelude::{Children, Parent};
+pub use hydrate::hydrate;
+pub use ssr::render_to_string;
 use std::{
 	any::TypeId,
 	cell::{Ref, RefMut},
 };
-#[cfg(not(feature = "insert-rule"))]
 use style_storage::{StyleStorage, STYLE_STORAGE};
 use sugars::hash;
 #[doc(hidden)] pub use world::World;
@@ -57,18 +72,18 @@ pub mod backtrace {
 		cell::RefCell,
 		panic::Location,
 	};
-	use once_cell::sync::Lazy;
 	use shrinkwraprs::Shrinkwrap;
 
-	pub static STORAGE_MAP: Lazy<BacktraceStorage> = Lazy::new(Default::default);
+	thread_local! {
+		static THREAD_STORAGE_MAP: &'static BacktraceStorage<'static> = Box::leak(Box::default());
+	}
+
+	pub static STORAGE_MAP: crate::world::PerThread<BacktraceStorage<'static>> = crate::world::PerThread::new(&THREAD_STORAGE_MAP);
 
 	#[repr(transparent)]
 	#[derive(Debug, Default)]
 	pub struct BacktraceStorage<'a>(pub RefCell<HashMap<TypeId, LocationMap<'a>>>);
 
-	unsafe impl Send for BacktraceStorage<'_> {}
-	unsafe impl Sync for BacktraceStorage<'_> {}
-
 	#[repr(transparent)]
 	#[derive(Debug, Default, Shrinkwrap)]
 	#[shrinkwrap(mutable)]
@@ -103,6 +118,18 @@ pub fn unregister_window(window_name: &str) {
 	style_storage.unregister_window(window_name);
 }
 
+/// Register a shadow root to also receive styles, `AsElement::attach_shadow` does this automatically
+pub fn register_shadow_root(shadow_root: &web_sys::ShadowRoot, name: &str) {
+	let style_storage = unsafe { &mut *STYLE_STORAGE.get() as &mut StyleStorage };
+	style_storage.register_shadow_root(shadow_root, name);
+}
+
+/// Removes a shadow root from the style storage
+pub fn unregister_shadow_root(name: &str) {
+	let style_storage = unsafe { &mut *STYLE_STORAGE.get() as &mut StyleStorage };
+	style_storage.unregister_shadow_root(name);
+}
+
 #[doc(hidden)]
 #[extend::ext(pub, name = MarkClassString)]
 impl<T: 'static> T {
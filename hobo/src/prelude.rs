@@ -3,9 +3,10 @@
 
 pub(crate) use crate::world::{World, WORLD};
 pub use crate::{
-	create::html::{StringValue, BasicAttrs, Placeholder},
+	aria::Aria,
+	create::html::{StringValue, BasicAttrs, Placeholder, Media},
 	css::{self, AppendProperty, F32},
-	dom_events::{RawDomEvents, DomEvents},
+	dom_events::{DomEvents, DomEventsGeneric, EventOptions, RawDomEvents, RawDomEventsGeneric},
 	element::{AsElement, Element},
 	entity::{AsEntity, Entity},
 	hierarchy::{Children, Parent},
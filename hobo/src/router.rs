@@ -0,0 +1,209 @@
+//! client-side routing on top of the history API, see the book for an example
+
+use crate::{prelude::*, dom_events::EventHandler};
+use futures_signals::signal::{Mutable, MutableSignalCloned, Signal, SignalExt};
+
+/// A decoded url without the origin
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Location {
+	/// Decoded, except for `%2F` and `%25` which stay escaped so that they aren't mistaken for separators or escapes
+	pub path: String,
+	pub query: Vec<(String, String)>,
+	pub hash: String,
+}
+
+// percent-decoding, malformed escapes and escapes of bytes in `keep` are kept as is
+// `+` is only a space in queries
+fn decode(x: &str, plus: bool, keep: &[u8]) -> String {
+	let x = x.as_bytes();
+	let mut res = Vec::with_capacity(x.len());
+	let mut i = 0;
+	while i < x.len() {
+		let escaped = x.get(i + 1..i + 3).filter(|_| x[i] == b'%')
+			.and_then(|x| std::str::from_utf8(x).ok())
+			.and_then(|x| u8::from_str_radix(x, 16).ok())
+			.filter(|x| !keep.contains(x));
+		if let Some(byte) = escaped {
+			res.push(byte);
+			i += 3;
+		} else {
+			res.push(if plus && x[i] == b'+' { b' ' } else { x[i] });
+			i += 1;
+		}
+	}
+	String::from_utf8_lossy(&res).into_owned()
+}
+
+// same as `encodeURIComponent` plus whatever is in `keep`, but doesn't need js so it works on the server too
+fn encode(x: &str, keep: &[u8]) -> String {
+	use std::fmt::Write;
+
+	let mut res = String::with_capacity(x.len());
+	for byte in x.bytes() {
+		if byte.is_ascii_alphanumeric() || b"-_.!~*'()".contains(&byte) || keep.contains(&byte) { res.push(byte as char); } else { write!(&mut res, "%{byte:02X}").unwrap(); }
+	}
+	res
+}
+
+impl Location {
+	pub fn new(path: impl Into<String>) -> Self { Self { path: path.into(), ..Self::default() } }
+
+	/// Parse a url like `/users/1?tab=posts#bio`
+	pub fn from_url(url: &str) -> Self {
+		let (rest, hash) = url.split_once('#').unwrap_or((url, ""));
+		let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+		Self {
+			path: decode(path, false, b"/%"),
+			query: query.split('&')
+				.filter(|x| !x.is_empty())
+				.map(|x| { let (k, v) = x.split_once('=').unwrap_or((x, "")); (decode(k, true, b""), decode(v, true, b"")) })
+				.collect(),
+			hash: decode(hash, false, b""),
+		}
+	}
+
+	/// The current location of the window
+	pub fn current() -> Self {
+		let location = web_sys::window().expect("no window").location();
+		Self::from_url(&format!(
+			"{}{}{}",
+			location.pathname().unwrap_or_default(),
+			location.search().unwrap_or_default(),
+			location.hash().unwrap_or_default(),
+		))
+	}
+
+	pub fn to_url(&self) -> String {
+		// `%` in the path is already an escape
+		let mut url = encode(&self.path, b"/%");
+		for (i, (k, v)) in self.query.iter().enumerate() {
+			url.push(if i == 0 { '?' } else { '&' });
+			url.push_str(&encode(k, b""));
+			url.push('=');
+			url.push_str(&encode(v, b""));
+		}
+		if !self.hash.is_empty() {
+			url.push('#');
+			url.push_str(&encode(&self.hash, b""));
+		}
+		url
+	}
+
+	/// Non-empty path segments, so that routes can be matched with slice patterns
+	pub fn segments(&self) -> Vec<&str> { self.path.split('/').filter(|x| !x.is_empty()).collect() }
+
+	/// The first value of a query parameter
+	pub fn query(&self, key: &str) -> Option<&str> { self.query.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str()) }
+
+	#[must_use]
+	pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self { self.query.push((key.into(), value.into())); self }
+
+	#[must_use]
+	pub fn with_hash(mut self, hash: impl Into<String>) -> Self { self.hash = hash.into(); self }
+}
+
+impl std::fmt::Display for Location {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(&self.to_url()) }
+}
+
+/// A typed route, usually an enum with a variant per page
+pub trait Route: Clone + PartialEq + 'static {
+	/// `Location`s that don't match anything should map to some kind of not found route
+	fn parse(location: &Location) -> Self;
+	fn format(&self) -> Location;
+}
+
+impl Route for Location {
+	fn parse(location: &Location) -> Self { location.clone() }
+	fn format(&self) -> Location { self.clone() }
+}
+
+/// Holds the current location, kept up to date with back and forward navigation
+///
+/// On the server it starts out as `/` and should be set to the requested url before rendering.
+pub struct Router {
+	pub location: Mutable<Location>,
+	_pop_state: Option<EventHandler>,
+}
+
+impl Default for Router {
+	fn default() -> Self {
+		if crate::ssr::ENABLED { return Self { location: Mutable::new(Location::new("/")), _pop_state: None }; }
+
+		let window: &web_sys::EventTarget = &web_sys::window().expect("no window");
+		Self {
+			location: Mutable::new(Location::current()),
+			_pop_state: Some(window.on_pop_state(|_| Router::resource().location.set_neq(Location::current()))),
+		}
+	}
+}
+
+pub fn location() -> Location { Router::resource_or_default().location.get_cloned() }
+
+pub fn location_signal() -> MutableSignalCloned<Location> { Router::resource_or_default().location.signal_cloned() }
+
+/// The current route, only fires when the route itself changes
+pub fn route_signal<R: Route>() -> impl Signal<Item = R> { location_signal().map(|x| R::parse(&x)).dedupe_cloned() }
+
+// there's no history on the server
+fn history() -> Option<web_sys::History> {
+	(!crate::ssr::ENABLED).then(|| web_sys::window().expect("no window").history().expect("no history"))
+}
+
+/// Go to `route`, adding an entry to the session history
+pub fn navigate<R: Route>(route: &R) {
+	let location = route.format();
+	if let Some(history) = history() {
+		history.push_state_with_url(&JsValue::NULL, "", Some(&location.to_url())).expect("can't push history state");
+	}
+	Router::resource_or_default().location.set_neq(location);
+}
+
+/// Go to `route`, replacing the current entry of the session history
+pub fn replace<R: Route>(route: &R) {
+	let location = route.format();
+	if let Some(history) = history() {
+		history.replace_state_with_url(&JsValue::NULL, "", Some(&location.to_url())).expect("can't replace history state");
+	}
+	Router::resource_or_default().location.set_neq(location);
+}
+
+impl crate::create::A {
+	/// Link to `route`, clicks navigate without reloading the page unless the user wants a new tab or window
+	pub fn set_route<R: Route>(&self, route: &R) {
+		self.set_attr(web_str::href(), route.format().to_url());
+		let route = route.clone();
+		let this = *self;
+		self.add_on_click(move |e| {
+			if e.default_prevented() || e.button() != 0 || e.ctrl_key() || e.meta_key() || e.shift_key() || e.alt_key() { return; }
+			let target = this.get_cmp::<web_sys::HtmlAnchorElement>().target();
+			if !target.is_empty() && target != "_self" { return; }
+			e.prevent_default();
+			navigate(&route);
+		});
+	}
+	#[must_use] pub fn route<R: Route>(self, route: &R) -> Self { self.set_route(route); self }
+}
+
+#[test]
+fn urls_round_trip() {
+	let location = Location::from_url("/a+b/x%2Fy/caf%C3%A9?q=a+b%2Bc&%E2%9C%93=1#top+1");
+	assert_eq!(location.path, "/a+b/x%2Fy/café");
+	assert_eq!(location.segments(), ["a+b", "x%2Fy", "café"]);
+	assert_eq!(location.query("q"), Some("a b+c"));
+	assert_eq!(location.query("✓"), Some("1"));
+	assert_eq!(location.hash, "top+1");
+	assert_eq!(location.to_url(), "/a%2Bb/x%2Fy/caf%C3%A9?q=a%20b%2Bc&%E2%9C%93=1#top%2B1");
+	assert_eq!(Location::from_url(&location.to_url()), location);
+
+	// malformed escapes are kept, invalid UTF-8 is replaced
+	let location = Location::from_url("/100%25/a%zz%?q=%E2%9C");
+	assert_eq!(location.path, "/100%25/a%zz%");
+	assert_eq!(location.query("q"), Some("\u{FFFD}"));
+	assert_eq!(location.to_url(), "/100%25/a%zz%?q=%EF%BF%BD");
+	assert_eq!(Location::from_url(&location.to_url()), location);
+
+	let location = Location::new("/a b").with_query("k", "v&w=x").with_hash("h#");
+	assert_eq!(location.to_url(), "/a%20b?k=v%26w%3Dx#h%23");
+	assert_eq!(Location::from_url(&location.to_url()), location);
+}
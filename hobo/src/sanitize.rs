@@ -0,0 +1,249 @@
+//! allowlist-based HTML sanitization
+//!
+//! `SanitizedHtml` can only be made by a `Sanitizer`, so `set_html` can't be handed markup that wasn't cleaned up:
+//!
+//! * tags that aren't allowed are dropped, but their text is kept, except for things like `<script>` whose content is dropped too
+//! * attributes that aren't allowed are dropped, event handler attributes are never allowed
+//! * urls in `href`, `src` and the like must be relative or use an allowed scheme
+//! * comments and doctypes are dropped, unclosed tags are closed
+
+use std::{collections::HashSet, fmt::Write};
+
+const DEFAULT_TAGS: &[&str] = &[
+	"a", "abbr", "b", "blockquote", "br", "caption", "cite", "code", "col", "colgroup", "dd", "del", "details", "dfn", "div", "dl", "dt",
+	"em", "figcaption", "figure", "h1", "h2", "h3", "h4", "h5", "h6", "hr", "i", "img", "ins", "kbd", "li", "mark", "ol", "p", "pre",
+	"q", "s", "samp", "small", "span", "strong", "sub", "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead", "time", "tr",
+	"u", "ul", "var",
+];
+
+const DEFAULT_ATTRIBUTES: &[&str] = &[
+	"href", "src", "alt", "title", "width", "height", "colspan", "rowspan", "span", "start", "reversed", "cite", "datetime", "open", "lang", "dir",
+];
+
+const DEFAULT_SCHEMES: &[&str] = &["http", "https", "mailto"];
+
+// attributes whose values are urls
+const URL_ATTRIBUTES: &[&str] = &["href", "src", "cite", "action", "formaction", "poster", "background", "xlink:href"];
+
+// elements whose content isn't text meant for the reader
+const DROPPED_CONTENT: &[&str] = &["script", "style", "template", "iframe", "object", "embed", "noscript", "noembed", "noframes", "textarea", "title", "xmp", "svg", "math"];
+
+/// HTML that went through a `Sanitizer`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct SanitizedHtml(String);
+
+impl SanitizedHtml {
+	/// Sanitize with the default `Sanitizer`
+	pub fn new(html: &str) -> Self { Sanitizer::default().sanitize(html) }
+	pub fn as_str(&self) -> &str { &self.0 }
+	pub fn into_string(self) -> String { self.0 }
+}
+
+impl std::fmt::Display for SanitizedHtml {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result { f.write_str(&self.0) }
+}
+
+/// Which tags, attributes and url schemes are allowed, the default allows common formatting like what markdown produces
+#[derive(Debug, Clone)]
+pub struct Sanitizer {
+	tags: HashSet<String>,
+	attributes: HashSet<String>,
+	schemes: HashSet<String>,
+}
+
+impl Default for Sanitizer {
+	fn default() -> Self {
+		Self {
+			tags: DEFAULT_TAGS.iter().map(|&x| x.to_owned()).collect(),
+			attributes: DEFAULT_ATTRIBUTES.iter().map(|&x| x.to_owned()).collect(),
+			schemes: DEFAULT_SCHEMES.iter().map(|&x| x.to_owned()).collect(),
+		}
+	}
+}
+
+impl Sanitizer {
+	/// Allows nothing, so only text is kept
+	pub fn empty() -> Self { Self { tags: HashSet::new(), attributes: HashSet::new(), schemes: HashSet::new() } }
+
+	#[must_use] pub fn allow_tags(mut self, tags: &[&str]) -> Self { self.tags.extend(tags.iter().map(|x| x.to_ascii_lowercase())); self }
+	#[must_use] pub fn deny_tags(mut self, tags: &[&str]) -> Self { for tag in tags { self.tags.remove(&tag.to_ascii_lowercase()); } self }
+	/// Event handlers like `onclick` are never allowed
+	#[must_use] pub fn allow_attributes(mut self, attributes: &[&str]) -> Self { self.attributes.extend(attributes.iter().map(|x| x.to_ascii_lowercase())); self }
+	#[must_use] pub fn deny_attributes(mut self, attributes: &[&str]) -> Self { for attr in attributes { self.attributes.remove(&attr.to_ascii_lowercase()); } self }
+	#[must_use] pub fn allow_schemes(mut self, schemes: &[&str]) -> Self { self.schemes.extend(schemes.iter().map(|x| x.to_ascii_lowercase())); self }
+	#[must_use] pub fn deny_schemes(mut self, schemes: &[&str]) -> Self { for scheme in schemes { self.schemes.remove(&scheme.to_ascii_lowercase()); } self }
+
+	fn url_allowed(&self, url: &str) -> bool {
+		// browsers ignore these when looking for the scheme, e.g. `java\tscript:`
+		let url = url.chars().filter(|x| !x.is_ascii_whitespace() && !x.is_control()).collect::<String>();
+		match url.find([':', '/', '?', '#']) {
+			Some(i) if url.as_bytes()[i] == b':' => self.schemes.contains(&url[..i].to_ascii_lowercase()),
+			_ => true,
+		}
+	}
+
+	fn attribute_allowed(&self, name: &str, value: &str) -> bool {
+		!name.starts_with("on") && self.attributes.contains(name) && (!URL_ATTRIBUTES.contains(&name) || self.url_allowed(value))
+	}
+
+	pub fn sanitize(&self, html: &str) -> SanitizedHtml {
+		let mut out = String::with_capacity(html.len());
+		let mut open = Vec::<String>::new();
+		let mut rest = html;
+
+		while let Some(start) = rest.find('<') {
+			escape(&mut out, &rest[..start], false);
+			rest = &rest[start..];
+
+			if let Some(comment) = rest.strip_prefix("<!--") {
+				rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+			} else if rest.starts_with("<!") || rest.starts_with("<?") {
+				rest = rest.find('>').map_or("", |end| &rest[end + 1..]);
+			} else if let Some(close) = rest.strip_prefix("</") {
+				let (name, after) = tag_name(close);
+				rest = after.find('>').map_or("", |end| &after[end + 1..]);
+				// closing something that isn't open is ignored, anything opened since is closed as well
+				if let Some(position) = open.iter().rposition(|x| *x == name) {
+					for tag in open.drain(position..).rev() { write!(out, "</{tag}>").unwrap(); }
+				}
+			} else if rest[1..].starts_with(|x: char| x.is_ascii_alphabetic()) {
+				let (name, after) = tag_name(&rest[1..]);
+				let (attributes, self_closing, after) = parse_attributes(after);
+				rest = after;
+
+				if !self.tags.contains(&name) {
+					if DROPPED_CONTENT.contains(&name.as_str()) && !self_closing { rest = skip_content(rest, &name); }
+					continue;
+				}
+
+				out.push('<');
+				out.push_str(&name);
+				for (attr, value) in attributes.into_iter().filter(|(attr, value)| self.attribute_allowed(attr, value)) {
+					out.push(' ');
+					out.push_str(&attr);
+					out.push_str("=\"");
+					escape(&mut out, &value, true);
+					out.push('"');
+				}
+				out.push('>');
+				if !crate::ssr::VOID_ELEMENTS.contains(&name.as_str()) { open.push(name); }
+			} else {
+				out.push_str("&lt;");
+				rest = &rest[1..];
+			}
+		}
+		escape(&mut out, rest, false);
+		for tag in open.into_iter().rev() { write!(out, "</{tag}>").unwrap(); }
+
+		SanitizedHtml(out)
+	}
+}
+
+// entities are kept as they are, so `&` doesn't need escaping in text
+fn escape(out: &mut String, x: &str, attr: bool) {
+	for c in x.chars() {
+		match c {
+			'&' if attr => out.push_str("&amp;"),
+			'"' if attr => out.push_str("&quot;"),
+			'<' => out.push_str("&lt;"),
+			'>' => out.push_str("&gt;"),
+			c => out.push(c),
+		}
+	}
+}
+
+fn tag_name(x: &str) -> (String, &str) {
+	let end = x.find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>').unwrap_or(x.len());
+	(x[..end].to_ascii_lowercase(), &x[end..])
+}
+
+// attribute names are lowercased and values decoded, `rest` starts right after the tag name
+fn parse_attributes(mut rest: &str) -> (Vec<(String, String)>, bool, &str) {
+	let mut attributes = Vec::new();
+	loop {
+		rest = rest.trim_start();
+		if let Some(after) = rest.strip_prefix("/>") { return (attributes, true, after); }
+		if let Some(after) = rest.strip_prefix('>') { return (attributes, false, after); }
+		if let Some(after) = rest.strip_prefix('/') { rest = after; continue; }
+		if rest.is_empty() { return (attributes, false, rest); }
+
+		let end = rest.find(|c: char| c.is_ascii_whitespace() || c == '/' || c == '>' || c == '=').unwrap_or(rest.len()).max(1);
+		let name = rest[..end].to_ascii_lowercase();
+		rest = rest[end..].trim_start();
+
+		let mut value = String::new();
+		if let Some(after) = rest.strip_prefix('=') {
+			let after = after.trim_start();
+			let (raw, after) = if let Some(quote @ ('"' | '\'')) = after.chars().next() {
+				let inner = &after[1..];
+				inner.find(quote).map_or((inner, ""), |end| (&inner[..end], &inner[end + 1..]))
+			} else {
+				let end = after.find(|c: char| c.is_ascii_whitespace() || c == '>').unwrap_or(after.len());
+				(&after[..end], &after[end..])
+			};
+			value = decode_entities(raw);
+			rest = after;
+		}
+		if !attributes.iter().any(|(x, _)| *x == name) { attributes.push((name, value)); }
+	}
+}
+
+fn skip_content<'a>(rest: &'a str, name: &str) -> &'a str {
+	let lower = rest.to_ascii_lowercase();
+	let close = format!("</{name}");
+	lower.find(&close).map_or("", |start| {
+		let after = &rest[start + close.len()..];
+		after.find('>').map_or("", |end| &after[end + 1..])
+	})
+}
+
+// enough to see what a url really is, e.g. `javascript&colon;` or `&#106;avascript:`
+fn decode_entities(x: &str) -> String {
+	let mut out = String::with_capacity(x.len());
+	let mut rest = x;
+	while let Some(start) = rest.find('&') {
+		out.push_str(&rest[..start]);
+		rest = &rest[start..];
+		let end = rest[1..].find(|c: char| !c.is_ascii_alphanumeric() && c != '#').map_or(rest.len(), |x| x + 1);
+		let entity = &rest[1..end];
+		let decoded = entity.strip_prefix('#').map_or_else(|| named_entity(entity), |number| number.strip_prefix(['x', 'X'])
+			.map_or_else(|| number.parse().ok(), |hex| u32::from_str_radix(hex, 16).ok())
+			.and_then(char::from_u32));
+		if let Some(c) = decoded {
+			out.push(c);
+			rest = rest[end..].strip_prefix(';').unwrap_or(&rest[end..]);
+		} else {
+			out.push('&');
+			rest = &rest[1..];
+		}
+	}
+	out.push_str(rest);
+	out
+}
+
+fn named_entity(x: &str) -> Option<char> {
+	match x {
+		"amp" => Some('&'), "lt" => Some('<'), "gt" => Some('>'), "quot" => Some('"'), "apos" => Some('\''),
+		"colon" => Some(':'), "tab" | "Tab" => Some('\t'), "newline" | "NewLine" => Some('\n'), "nbsp" => Some('\u{a0}'),
+		_ => None,
+	}
+}
+
+#[test]
+fn sanitizes() {
+	use crate::{create as e, prelude::*};
+
+	let html = SanitizedHtml::new(r#"<p class="x" onclick="evil()">Hi <b>there<script>alert(1)</script></b><a href="java&#115;cript:alert(1)">a</a><a href='https://example.com?a=1&b="2"'>b</a><unknown>text</unknown><img src=x.png alt=pic /><!-- note --><ul><li>unclosed"#);
+	assert_eq!(html.as_str(), r#"<p>Hi <b>there</b><a>a</a><a href="https://example.com?a=1&amp;b=&quot;2&quot;">b</a>text<img src="x.png" alt="pic"><ul><li>unclosed</li></ul></p>"#);
+	assert_eq!(Sanitizer::empty().sanitize("<h1>1 < 2</h1>").as_str(), "1 &lt; 2");
+
+	let div = e::div().child(e::span()).html(&SanitizedHtml::new("<em>hi</em>"));
+	assert!(div.get_cmp::<Children>().is_empty());
+	assert_eq!(crate::ssr::render_html(div), "<div><em>hi</em></div>");
+
+	// dynamic children stop updating
+	let items = futures_signals::signal_vec::MutableVec::new_with_values(vec![1]);
+	let div = e::div().children_signal_vec(items.signal_vec(), |x| e::span().text(x.to_string())).html(&SanitizedHtml::new("<em>hi</em>"));
+	items.lock_mut().push(2);
+	assert_eq!(crate::ssr::render_html(div), "<div><em>hi</em></div>");
+}
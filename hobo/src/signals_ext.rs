@@ -5,10 +5,20 @@ use futures_signals::{
 	signal_vec::{SignalVec, SignalVecExt, VecDiff},
 	CancelableFutureHandle,
 };
-use wasm_bindgen_futures::spawn_local as spawn;
 
 pub type Subscription = DiscardOnDrop<CancelableFutureHandle>;
 
+// without a browser or while hydrating the future is polled in place, see `ssr` and `hydrate`
+pub(crate) fn spawn(fut: impl std::future::Future<Output = ()> + 'static) {
+	if crate::ssr::ENABLED {
+		crate::ssr::spawn(fut);
+	} else if crate::hydrate::is_hydrating() {
+		crate::hydrate::spawn(fut);
+	} else {
+		wasm_bindgen_futures::spawn_local(fut);
+	}
+}
+
 pub trait SignalExt2: Signal {
 	fn subscribe<F>(self, callback: F) -> Subscription where
 		F: FnMut(Self::Item) + 'static,
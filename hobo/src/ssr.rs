@@ -0,0 +1,265 @@
+//! server-side rendering
+//!
+//! When not compiled to wasm, elements are built in memory instead of in a DOM, so the same component functions
+//! can be rendered to a string with `render_to_string`, e.g. on a server or in a native `cargo test`.
+//! Signals are rendered with their current values, events are ignored.
+
+use crate::prelude::*;
+use std::{cell::RefCell, fmt::Write, future::Future, pin::Pin, task::{Context, Poll, Waker}};
+
+pub(crate) const ENABLED: bool = cfg!(not(target_arch = "wasm32"));
+
+// what a `web_sys::Node` would have been
+pub(crate) enum SsrNode {
+	Element {
+		tag: &'static str,
+		// in insertion order, same as the DOM
+		attrs: Vec<(String, String)>,
+		text: Option<String>,
+		// `text` is markup from `set_html_unchecked`
+		html: bool,
+		// children that `text` replaced, same as `set_text_content` would've removed their nodes
+		replaced: Vec<Entity>,
+	},
+	Comment(String),
+}
+
+// a shadow root is a fragment on the server, rendered as declarative shadow DOM
+pub(crate) struct SsrShadowRoot(pub(crate) web_sys::ShadowRootMode);
+
+pub(crate) fn element(tag: &'static str) -> Entity {
+	let entity = WORLD.new_entity();
+	WORLD.storage_mut::<SsrNode>().add(entity, SsrNode::Element { tag, attrs: Vec::new(), text: None, html: false, replaced: Vec::new() });
+	entity
+}
+
+pub(crate) fn comment(text: &str) -> Entity {
+	let entity = WORLD.new_entity();
+	WORLD.storage_mut::<SsrNode>().add(entity, SsrNode::Comment(text.to_owned()));
+	entity
+}
+
+pub(crate) fn get_attr(entity: Entity, key: &str) -> Option<String> {
+	let SsrNode::Element { attrs, .. } = &*entity.get_cmp::<SsrNode>() else { return None; };
+	attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone())
+}
+
+pub(crate) fn set_attr(entity: Entity, key: &str, value: &str) {
+	let SsrNode::Element { attrs, .. } = &mut *entity.get_cmp_mut::<SsrNode>() else { return; };
+	if let Some((_, v)) = attrs.iter_mut().find(|(k, _)| k == key) { value.clone_into(v); } else { attrs.push((key.to_owned(), value.to_owned())); }
+}
+
+pub(crate) fn remove_attr(entity: Entity, key: &str) {
+	let SsrNode::Element { attrs, .. } = &mut *entity.get_cmp_mut::<SsrNode>() else { return; };
+	attrs.retain(|(k, _)| k != key);
+}
+
+pub(crate) fn text(entity: Entity) -> Option<String> {
+	match &*entity.get_cmp::<SsrNode>() {
+		SsrNode::Element { text, .. } => text.clone(),
+		SsrNode::Comment(text) => Some(text.clone()),
+	}
+}
+
+pub(crate) fn set_text(entity: Entity, value: &str) {
+	let children = entity.try_get_cmp::<Children>().map(|x| x.0.clone()).unwrap_or_default();
+	match &mut *entity.get_cmp_mut::<SsrNode>() {
+		SsrNode::Element { text, html, replaced, .. } => { *text = Some(value.to_owned()); *html = false; *replaced = children; },
+		SsrNode::Comment(text) => value.clone_into(text),
+	}
+}
+
+pub(crate) fn set_html(entity: Entity, value: &str) {
+	let SsrNode::Element { text, html, replaced, .. } = &mut *entity.get_cmp_mut::<SsrNode>() else { return; };
+	*text = Some(value.to_owned());
+	*html = true;
+	// the children are removed by `set_html_unchecked`, except for portals which don't render here anyway
+	replaced.clear();
+}
+
+thread_local! {
+	static PENDING: RefCell<Vec<Pin<Box<dyn Future<Output = ()>>>>> = RefCell::default();
+}
+
+fn poll(fut: &mut Pin<Box<dyn Future<Output = ()>>>) -> Poll<()> { fut.as_mut().poll(&mut Context::from_waker(Waker::noop())) }
+
+// polled right away so that signals apply their initial values, the rest is polled again before rendering
+pub(crate) fn spawn(fut: impl Future<Output = ()> + 'static) {
+	let mut fut: Pin<Box<dyn Future<Output = ()>>> = Box::pin(fut);
+	if poll(&mut fut).is_pending() { PENDING.with_borrow_mut(|x| x.push(fut)); }
+}
+
+/// Poll spawned futures until they stop spawning new ones, so that changes made after building the elements are applied
+///
+/// Called by `render_to_string`.
+pub fn run_pending() {
+	loop {
+		let pending = PENDING.take();
+		let mut still_pending = pending.into_iter().filter_map(|mut x| poll(&mut x).is_pending().then_some(x)).collect::<Vec<_>>();
+		let spawned = PENDING.with_borrow_mut(|x| { let spawned = !x.is_empty(); x.append(&mut still_pending); spawned });
+		if !spawned { break; }
+	}
+}
+
+pub(crate) const VOID_ELEMENTS: &[&str] = &["area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr"];
+
+fn escape(out: &mut String, x: &str, attr: bool) {
+	for c in x.chars() {
+		match c {
+			'&' => out.push_str("&amp;"),
+			'<' => out.push_str("&lt;"),
+			'>' => out.push_str("&gt;"),
+			'"' if attr => out.push_str("&quot;"),
+			c => out.push(c),
+		}
+	}
+}
+
+// `<!--`, `-->` and `--!>` can't be inside of a comment, it can't start with `>` or `->` or end with `<!-`
+fn escape_comment(x: &str) -> String {
+	let mut res = x.to_owned();
+	while res.contains("--") { res = res.replace("--", "- -"); }
+	if res.starts_with(['>', '-']) { res.insert(0, ' '); }
+	if res.ends_with('-') { res.push(' '); }
+	res
+}
+
+// the content of `<script>` and `<style>` isn't escaped, so it only has to not close the element early
+fn escape_raw_text(out: &mut String, x: &str, tag: &str) {
+	let close = format!("</{tag}");
+	let lower = x.to_ascii_lowercase();
+	let mut last = 0;
+	for (i, _) in lower.match_indices(&close) {
+		out.push_str(&x[last..i]);
+		out.push_str("<\\/");
+		last = i + 2;
+	}
+	out.push_str(&x[last..]);
+}
+
+fn render_element(out: &mut String, entity: Entity) {
+	// portals belong to some other node
+	let mut children = entity.try_get_cmp::<Children>().map(|x| x.0.clone()).unwrap_or_default()
+		.into_iter().filter(|&x| !crate::element::outside_parent_dom(x)).collect::<Vec<_>>();
+	let tag = {
+		let Some(node) = entity.try_get_cmp::<SsrNode>() else {
+			let mode = entity.try_get_cmp::<SsrShadowRoot>().map(|x| x.0);
+			if let Some(mode) = mode {
+				// styles of the page don't apply inside, so it gets all of them
+				let mode = if mode == web_sys::ShadowRootMode::Closed { "closed" } else { "open" };
+				write!(out, "<template shadowrootmode=\"{mode}\">{}", crate::style_storage::rendered_styles()).unwrap();
+			}
+			// fragments
+			for child in children { render_element(out, child); }
+			if mode.is_some() { out.push_str("</template>"); }
+			return;
+		};
+
+		match &*node {
+			SsrNode::Comment(text) => { write!(out, "<!--{}-->", escape_comment(text)).unwrap(); return; },
+			SsrNode::Element { tag, attrs, text, html, replaced } => {
+				write!(out, "<{tag}").unwrap();
+				for (k, v) in attrs {
+					write!(out, " {k}=\"").unwrap();
+					escape(out, v, true);
+					out.push('"');
+				}
+				out.push('>');
+				if VOID_ELEMENTS.contains(tag) { return; }
+
+				// same as `set_text_content`, the text replaces whatever children there were at the time, children added later come after it
+				if let Some(text) = text {
+					children.retain(|x| !replaced.contains(x));
+					if *html { out.push_str(text); } else if matches!(*tag, "script" | "style") { escape_raw_text(out, text, tag); } else { escape(out, text, false); }
+				}
+				*tag
+			},
+		}
+	};
+
+	// the shadow root's `<template>` has to be the first child of its host
+	children.sort_by_key(|x| !x.has_cmp::<SsrShadowRoot>());
+	for child in children { render_element(out, child); }
+	write!(out, "</{tag}>").unwrap();
+}
+
+/// `root` and its descendants as HTML
+pub fn render_html(root: impl AsElement) -> String {
+	run_pending();
+	let mut out = String::new();
+	render_element(&mut out, root.as_entity());
+	out
+}
+
+/// A `<style>` element for every class position used so far, `data-hobo-styles` lists the hashes of the styles inside
+pub fn render_styles() -> String {
+	run_pending();
+	crate::style_storage::rendered_styles()
+}
+
+/// `<style>` elements followed by the HTML of `root`, elements are only rendered when not compiled to wasm
+///
+/// `root` is removed and the styles are forgotten afterwards, so that every call renders only its own styles
+/// and a long-running server doesn't keep every page it rendered.
+/// Every thread has its own elements, so `root` has to be built on the thread that renders it.
+pub fn render_to_string(root: impl AsElement) -> String {
+	let html = render_html(&root);
+	let res = render_styles() + &html;
+	root.remove();
+	run_pending();
+	crate::style_storage::clear_rendered_styles();
+	crate::aria::reset_ids();
+	res
+}
+
+#[test]
+fn renders_elements() {
+	use crate::create as e;
+
+	let text = futures_signals::signal::Mutable::new("before");
+	let root = e::div()
+		.class(css::style!(.& { css::background_color::rgb(0xFF_00_00) }))
+		.attr("data-quote", "\"a\" & b")
+		.child(e::span().text("1 < 2"))
+		.child(e::input().attr("value", "x"))
+		.child(e::p().text_signal(text.signal()));
+	text.set("after");
+
+	let html = render_html(root);
+	let class = root.get_attr("class").unwrap();
+	assert_eq!(html, format!(r#"<div class="{class}" data-quote="&quot;a&quot; &amp; b"><span>1 &lt; 2</span><input value="x"><p>after</p></div>"#));
+	assert!(render_styles().contains(&format!(".{class}{{background-color:#ff0000ff;}}")));
+}
+
+#[test]
+fn escapes_raw_text() {
+	use crate::create as e;
+
+	let root = e::div()
+		.child(e::script().text("let x = '</SCRIPT><b>';"))
+		.child(e::div().child(e::span()).text("text").child(e::i()));
+	assert_eq!(render_html(root), r"<div><script>let x = '<\/SCRIPT><b>';</script><div>text<i></i></div></div>");
+	assert_eq!(escape_comment("->a---b-"), " ->a- - -b- ");
+
+	let first = render_to_string(e::div().class(css::class!(css::opacity::val(0.25))));
+	assert!(first.contains("opacity:0.25"));
+	let second = render_to_string(e::div().class(css::class!(css::opacity::val(0.75))));
+	assert!(second.contains("opacity:0.75") && !second.contains("opacity:0.25"));
+	assert_eq!(render_to_string(e::div().class(css::class!(css::opacity::val(0.25)))), first);
+}
+
+#[test]
+fn renders_on_several_threads() {
+	use crate::create as e;
+
+	let threads = (0..4_u32).map(|i| std::thread::spawn(move || (0..50).map(|j| {
+		let text = format!("{i}-{j}");
+		render_to_string(e::div().class(css::class!(css::z_index::val(i64::from(i)))).child(e::span().text(&text)))
+	}).collect::<Vec<_>>())).collect::<Vec<_>>();
+
+	for (i, thread) in threads.into_iter().enumerate() {
+		for (j, html) in thread.join().unwrap().into_iter().enumerate() {
+			assert!(html.contains(&format!("z-index:{i};")) && html.ends_with(&format!("<span>{i}-{j}</span></div>")));
+		}
+	}
+}
@@ -1,5 +1,4 @@
-use crate::{prelude::*, racy_cell::RacyCell};
-use once_cell::sync::Lazy;
+use crate::{prelude::*, racy_cell::RacyCell, world::PerThread};
 use std::{collections::{HashMap, BTreeSet}, hash::{Hash, Hasher}};
 use sugars::hmap;
 
@@ -13,34 +12,141 @@ pub struct StyleStorage {
 	/// See [fetch](Self::fetch) for both hashing and re-use checks.
 	inserted_style_hashes: BTreeSet<u64>,
 
-	/// Map representing the <style> elements in each window.
+	/// Map representing the <style> elements in each window or shadow root.
 	///
 	/// * key:     `String`                         - identifier, usually the name of the window.
-	/// * value.0: `web_sys::Document`              - The main document of the window, to which head's <style> elements will be appended.
-	/// * value.1: `Vec<web_sys::HtmlStyleElement>` - The <style> elements in this window's head.
+	/// * value.0: `web_sys::Node`                  - The main document of the window, to which head's <style> elements will be appended, or a shadow root.
+	/// * value.1: `Vec<web_sys::HtmlStyleElement>` - The <style> elements in this window's head or in the shadow root.
 	#[cfg(not(feature = "insert-rule"))]
-	style_elements: HashMap<String, (web_sys::Document, Vec<web_sys::HtmlStyleElement>)>,
+	style_elements: HashMap<String, (web_sys::Node, Vec<web_sys::HtmlStyleElement>)>,
 
 	#[cfg(feature = "insert-rule")]
-	style_elements: HashMap<String, (web_sys::Document, Vec<web_sys::CssStyleSheet>)>,
+	style_elements: HashMap<String, (web_sys::Node, Vec<web_sys::CssStyleSheet>)>,
+
+	/// Hashes and css of the styles in each class position, collected instead of `style_elements` for server-side rendering.
+	ssr_styles: Vec<(Vec<u64>, String)>,
+}
+
+thread_local! {
+	static THREAD_STYLE_STORAGE: &'static RacyCell<StyleStorage> = Box::leak(Box::new(RacyCell::new(new_style_storage())));
 }
 
+// per thread like `WORLD`
 #[allow(clippy::redundant_pub_crate)]
-pub(crate) static STYLE_STORAGE: Lazy<RacyCell<StyleStorage>> = Lazy::new(|| RacyCell::new(StyleStorage {
-	inserted_style_hashes: BTreeSet::new(),
-	style_elements: hmap!["default".to_owned() => {
+pub(crate) static STYLE_STORAGE: PerThread<RacyCell<StyleStorage>> = PerThread::new(&THREAD_STYLE_STORAGE);
+
+fn new_style_storage() -> StyleStorage {
+	if crate::ssr::ENABLED { return StyleStorage::default(); }
+
+	StyleStorage {
+		inserted_style_hashes: BTreeSet::new(),
+		ssr_styles: Vec::new(),
+		style_elements: hmap!["default".to_owned() => {
+			let dom = web_sys::window().expect("no window").document().expect("no document");
+			let mut elements = server_rendered_style_elements(&dom);
+			if elements.is_empty() {
+				let head = dom.head().expect("dom has no head");
+				let element = create_style_element(&dom);
+				head.append_child(&element).expect("can't append child");
+				elements.push(element);
+			}
+
+			(
+				dom.into(),
+				#[cfg(not(feature = "insert-rule"))] elements,
+				#[cfg(feature = "insert-rule")] elements.into_iter().map(|x| x.sheet().unwrap().unchecked_into::<web_sys::CssStyleSheet>()).collect(),
+			)
+		}],
+	}.with_server_rendered_hashes()
+}
+
+// `<style>` elements from `render_to_string`, in class position order
+fn server_rendered_style_elements(dom: &web_sys::Document) -> Vec<web_sys::HtmlStyleElement> {
+	let elements = dom.query_selector_all("style[data-hobo-styles]").expect("can't query style elements");
+	(0..elements.length()).filter_map(|i| elements.get(i)).map(JsCast::unchecked_into).collect()
+}
+
+// marked like server-rendered ones, so that `hydrate` doesn't mistake them for content when they end up next to it
+fn create_style_element(dom: &web_sys::Document) -> web_sys::HtmlStyleElement {
+	let element = dom.create_element(web_str::style()).expect("can't create style element");
+	element.set_attribute("data-hobo-styles", "").expect("can't set attribute");
+	element.unchecked_into()
+}
+
+// the document that `<style>` elements for `root` are created in
+fn document(root: &web_sys::Node) -> web_sys::Document {
+	root.dyn_ref::<web_sys::Document>().cloned().unwrap_or_else(|| root.owner_document().expect("shadow root has no document"))
+}
+
+// a `<style>` element for a new class position in a window or a shadow root
+#[cfg(not(feature = "insert-rule"))]
+fn new_style_element(root: &web_sys::Node, last: Option<&web_sys::HtmlStyleElement>) -> web_sys::HtmlStyleElement {
+	let dom = document(root);
+	let style_element = create_style_element(&dom);
+	// server-rendered styles aren't necessarily in the head, and later class positions have to come later in the document
+	if let Some(last) = last {
+		last.after_with_node_1(&style_element).expect("can't insert style element");
+	} else if root.has_type::<web_sys::ShadowRoot>() {
+		root.append_child(&style_element).expect("can't append child");
+	} else {
+		dom.head().expect("dom has no head").append_child(&style_element).expect("can't append child");
+	}
+	style_element
+}
+
+// style elements in a shadow root only get a sheet once the shadow root is in the document, so shadow roots adopt constructed sheets instead
+#[cfg(feature = "insert-rule")]
+fn new_style_sheet(root: &web_sys::Node) -> web_sys::CssStyleSheet {
+	if root.has_type::<web_sys::ShadowRoot>() {
+		let sheet = web_sys::CssStyleSheet::new().expect("can't create style sheet");
+		let key = JsValue::from_str("adoptedStyleSheets");
+		let sheets = js_sys::Array::from(&js_sys::Reflect::get(root, &key).expect("can't get adopted style sheets"));
+		sheets.push(&sheet);
+		js_sys::Reflect::set(root, &key, &sheets).expect("can't set adopted style sheets");
+		return sheet;
+	}
+
+	let dom = document(root);
+	let style_element = create_style_element(&dom);
+	dom.head().expect("dom has no head").append_child(&style_element).expect("can't append child");
+	style_element.sheet().unwrap().unchecked_into()
+}
+
+impl StyleStorage {
+	// so that styles that came with server-rendered markup aren't inserted again
+	fn with_server_rendered_hashes(mut self) -> Self {
 		let dom = web_sys::window().expect("no window").document().expect("no document");
-		let head = dom.head().expect("dom has no head");
-		let element = dom.create_element(web_str::style()).expect("can't create style element");
-		head.append_child(&element).expect("can't append child");
+		for element in server_rendered_style_elements(&dom) {
+			let hashes = element.get_attribute("data-hobo-styles").unwrap_or_default();
+			self.inserted_style_hashes.extend(hashes.split_whitespace().filter_map(|x| u64::from_str_radix(x, 16).ok()));
+		}
+		self
+	}
+}
 
-		(
-			dom,
-			#[cfg(not(feature = "insert-rule"))] vec![element.unchecked_into::<web_sys::HtmlStyleElement>()],
-			#[cfg(feature = "insert-rule")] vec![element.unchecked_into::<web_sys::HtmlStyleElement>().sheet().unwrap().unchecked_into::<web_sys::CssStyleSheet>()],
-		)
-	}],
-}));
+// class names have to be the same in the server and in the browser, so the hash can't depend on the platform, the build or the toolchain
+// FNV-1a is fully specified, integers are hashed as little endian and `usize` as `u64` so that every platform agrees
+struct StableHasher(u64);
+
+impl Default for StableHasher {
+	fn default() -> Self { Self(0xcbf2_9ce4_8422_2325) }
+}
+
+impl Hasher for StableHasher {
+	fn finish(&self) -> u64 { self.0 }
+	fn write(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
+			self.0 ^= u64::from(byte);
+			self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+		}
+	}
+	fn write_u16(&mut self, i: u16) { self.write(&i.to_le_bytes()); }
+	fn write_u32(&mut self, i: u32) { self.write(&i.to_le_bytes()); }
+	fn write_u64(&mut self, i: u64) { self.write(&i.to_le_bytes()); }
+	fn write_u128(&mut self, i: u128) { self.write(&i.to_le_bytes()); }
+	fn write_usize(&mut self, i: usize) { self.write_u64(i as u64); }
+	fn write_isize(&mut self, i: isize) { self.write_i64(i as i64); }
+}
 
 #[extend::ext]
 impl css::Style {
@@ -86,7 +192,7 @@ impl StyleStorage {
 		style.sort_properties();
 
 		// u64 hash from style + ordinal
-		let mut hasher = ahash::AHasher::default();
+		let mut hasher = StableHasher::default();
 		style.hash(&mut hasher);
 		ordinal.hash(&mut hasher);
 		let id = hasher.finish();
@@ -105,18 +211,21 @@ impl StyleStorage {
 		#[cfg(not(feature = "insert-rule"))]
 		let style_string = style.to_string();
 
-		// for each window
-		for (dom, ordered_style_elements) in self.style_elements.values_mut() {
-			if ordered_style_elements.get(ordinal).is_none() {
-				let style_element = dom.create_element(web_str::style()).expect("can't create style element");
-				let head = dom.head().expect("dom has no head");
-				head.append_child(&style_element).expect("can't append child");
+		if crate::ssr::ENABLED {
+			if self.ssr_styles.len() <= ordinal { self.ssr_styles.resize_with(ordinal + 1, Default::default); }
+			let (ids, css) = &mut self.ssr_styles[ordinal];
+			ids.push(id);
+			css.push_str(&style.to_string());
+		}
 
+		// for each window and shadow root
+		for (root, ordered_style_elements) in self.style_elements.values_mut() {
+			if ordered_style_elements.get(ordinal).is_none() {
 				#[cfg(not(feature = "insert-rule"))]
-				ordered_style_elements.push(style_element.unchecked_into::<web_sys::HtmlStyleElement>());
+				ordered_style_elements.push(new_style_element(root, ordered_style_elements.last()));
 
 				#[cfg(feature = "insert-rule")]
-				ordered_style_elements.push(style_element.unchecked_into::<web_sys::HtmlStyleElement>().sheet().unwrap().unchecked_into::<web_sys::CssStyleSheet>());
+				ordered_style_elements.push(new_style_sheet(root));
 			}
 
 			// insert the stringified generated css into the style tag
@@ -146,14 +255,73 @@ impl StyleStorage {
 
 		// Re-create each existing <style> element from the default window into the new window
 		for default_window_style_index in 0..self.style_elements.get("default").expect("no default window").1.len() {
-			let new_style_element = dom.create_element(web_str::style()).expect("can't create style element");
+			let new_style_element = create_style_element(&dom);
 			head.append_child(&new_style_element).expect("can't append child");
 
 			// re-add all the already existing styles,
 			// especially necessary for re-registering a previously closed window
 			let style_element = &self.style_elements.get("default").expect("no default window").1[default_window_style_index];
 			new_style_element.set_inner_html(&style_element.inner_html());
-			self.style_elements.entry(window_name.to_owned()).or_insert((dom.clone(), Vec::new())).1.push(new_style_element.unchecked_into::<web_sys::HtmlStyleElement>());
+			self.style_elements.entry(window_name.to_owned()).or_insert((dom.clone().into(), Vec::new())).1.push(new_style_element);
 		}
 	}
+
+	pub fn register_shadow_root(&mut self, shadow_root: &web_sys::ShadowRoot, name: &str) {
+		let root: &web_sys::Node = shadow_root.as_ref();
+
+		// same as with windows, styles that already exist are copied
+		let default = &self.style_elements.get("default").expect("no default window").1;
+		#[cfg(not(feature = "insert-rule"))]
+		let elements = default.iter().fold(Vec::new(), |mut elements, default_element| {
+			let element = new_style_element(root, elements.last());
+			element.set_inner_html(&default_element.inner_html());
+			elements.push(element);
+			elements
+		});
+
+		#[cfg(feature = "insert-rule")]
+		let elements = default.iter().map(|default_sheet| {
+			let sheet = new_style_sheet(root);
+			let rules = default_sheet.css_rules().expect("can't get css rules");
+			for i in 0..rules.length() {
+				if let Some(rule) = rules.get(i) { sheet.insert_rule_with_index(&rule.css_text(), sheet.css_rules().expect("can't get css rules").length()).unwrap(); }
+			}
+			sheet
+		}).collect();
+
+		self.style_elements.insert(name.to_owned(), (root.clone(), elements));
+	}
+
+	pub fn unregister_shadow_root(&mut self, name: &str) {
+		self.style_elements.remove(name);
+	}
+}
+
+// a `<style>` element per class position, in the same order they would've been appended to the head
+pub fn rendered_styles() -> String {
+	use std::fmt::Write;
+
+	let style_storage = unsafe { &*STYLE_STORAGE.get() as &StyleStorage };
+	let mut res = String::new();
+	for (hashes, css) in &style_storage.ssr_styles {
+		let hashes = hashes.iter().map(|x| format!("{x:x}")).collect::<Vec<_>>().join(" ");
+		write!(&mut res, "<style data-hobo-styles=\"{hashes}\">{css}</style>").unwrap();
+	}
+	res
+}
+
+// styles are rendered once per page
+pub fn clear_rendered_styles() {
+	let style_storage = unsafe { &mut *STYLE_STORAGE.get() as &mut StyleStorage };
+	style_storage.inserted_style_hashes.clear();
+	style_storage.ssr_styles.clear();
+}
+
+#[test]
+fn stable_class_names() {
+	let mut hasher = StableHasher::default();
+	hasher.write(b"a");
+	assert_eq!(hasher.finish(), 0xaf63_dc4c_8601_ec8c);
+
+	assert_eq!(StyleStorage::default().fetch(css::class!(css::opacity::val(0.5)), 0), "s-49501bb385c39554");
 }
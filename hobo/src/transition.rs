@@ -0,0 +1,117 @@
+//! enter and leave transitions
+//!
+//! An element with a transition gets its enter class when it's built. When it's removed, e.g. by `child_signal` or
+//! `replace_with`, the entity is gone right away, but its node is kept in the DOM with the leave class until its
+//! animation or transition ends, or until `duration` passes in case it never starts:
+//!
+//! ```ignore
+//! e::div().transition(
+//!     css::class!(css::animation_name::str("fade-in"), css::animation_duration::dur(200)),
+//!     css::class!(css::animation_name::str("fade-out"), css::animation_duration::dur(200), css::animation_fill_mode::forwards),
+//!     Duration::from_millis(300),
+//! )
+//! ```
+//!
+//! Enter styles should use an animation since the element gets the class before it's attached.
+//! Nothing happens on the server and the enter class isn't applied to hydrated elements.
+
+use crate::{prelude::*, element::{Classes, tag_hash}};
+use std::{cell::{Cell, RefCell}, collections::HashMap, rc::Rc, time::Duration};
+
+struct Leave {
+	class: String,
+	duration: Duration,
+}
+
+#[derive(Hash)]
+struct Enter;
+
+thread_local! {
+	static LEAVES: RefCell<HashMap<Entity, Leave>> = RefCell::default();
+	// while an element with a leave transition is removed, the nodes of its descendants stay inside of it
+	static KEEPING_NODES: Cell<u32> = const { Cell::new(0) };
+}
+
+// calls `f` once the element's own animation or transition ends or after `timeout`, whichever is first
+fn on_end(element: &web_sys::Element, timeout: Duration, f: impl FnOnce() + 'static) {
+	let window = web_sys::window().expect("no window");
+	let listener = Rc::new(RefCell::new(None::<js_sys::Function>));
+	let timer = Rc::new(Cell::new(None::<i32>));
+	let mut f = Some(f);
+
+	let callback = Closure::<dyn FnMut(JsValue)>::new({
+		let (element, window, listener, timer) = (element.clone(), window.clone(), Rc::clone(&listener), Rc::clone(&timer));
+		move |e: JsValue| {
+			// ends of descendants' animations bubble up
+			if e.dyn_ref::<web_sys::Event>().is_some_and(|e| e.target().as_ref() != Some(element.unchecked_ref())) { return; }
+			let Some(f) = f.take() else { return; };
+
+			if let Some(timer) = timer.take() { window.clear_timeout_with_handle(timer); }
+			// dropping the function breaks the cycle between it and the closure
+			if let Some(listener) = listener.take() {
+				for name in [web_str::animationend(), web_str::transitionend()] {
+					element.remove_event_listener_with_callback(name, &listener).expect("can't remove event listener");
+				}
+			}
+			f();
+		}
+	}).into_js_value().unchecked_into::<js_sys::Function>();
+
+	for name in [web_str::animationend(), web_str::transitionend()] {
+		element.add_event_listener_with_callback(name, &callback).expect("can't add event listener");
+	}
+	let millis = i32::try_from(timeout.as_millis()).unwrap_or(i32::MAX);
+	timer.set(Some(window.set_timeout_with_callback_and_timeout_and_arguments_0(&callback, millis).expect("can't set timeout")));
+	*listener.borrow_mut() = Some(callback);
+}
+
+pub fn set(element: Element, enter: css::Style, leave: css::Style, duration: Duration) {
+	if element.is_dead() { log::warn!("set_transition dead entity {:?}", element.as_entity()); return; }
+	if element.has_no_element("set_transition") { return; }
+	if crate::ssr::ENABLED { return; }
+
+	let entity = element.as_entity();
+	// the leave class comes after every other class, so it takes precedence
+	let ordinal = entity.try_get_cmp::<Classes>().map_or(0, |x| x.styles.len()) + 1;
+	let style_storage = unsafe { &mut *crate::STYLE_STORAGE.get() as &mut crate::StyleStorage };
+	let class = style_storage.fetch(leave, ordinal);
+	LEAVES.with_borrow_mut(|x| x.insert(entity, Leave { class, duration }));
+
+	if crate::hydrate::is_hydrating() { return; }
+	element.set_class_tagged(Enter, enter);
+	on_end(&element.get_cmp::<web_sys::Element>(), duration, move || {
+		if let Some(mut classes) = entity.try_get_cmp_mut::<Classes>() { classes.styles.remove(&tag_hash(Enter)); }
+	});
+}
+
+// removing children of an element that's about to leave doesn't detach their nodes
+pub fn remove_children(entity: Entity, f: impl FnOnce()) {
+	let leaving = LEAVES.with_borrow(|x| x.contains_key(&entity));
+	if leaving { KEEPING_NODES.set(KEEPING_NODES.get() + 1); }
+	f();
+	if leaving { KEEPING_NODES.set(KEEPING_NODES.get() - 1); }
+}
+
+// called instead of `element.remove()` when an element's entity is removed
+pub fn remove_node(entity: Entity, element: &web_sys::Element) {
+	let leave = LEAVES.with_borrow_mut(|x| x.remove(&entity));
+	if KEEPING_NODES.get() > 0 { return; }
+	let Some(Leave { class, duration }) = leave else { element.remove(); return; };
+
+	element.class_list().add_1(&class).expect("can't add class");
+	let node = element.clone();
+	on_end(element, duration, move || node.remove());
+}
+
+#[test]
+fn nothing_happens_on_the_server() {
+	use crate::create as e;
+
+	let leaving = e::span().transition(css::class!(css::opacity::val(0.)), css::class!(css::opacity::val(0.5)), Duration::from_millis(100));
+	let root = e::div().child(leaving).child(e::b());
+	assert_eq!(crate::ssr::render_html(root), "<div><span></span><b></b></div>");
+
+	leaving.remove();
+	assert_eq!(crate::ssr::render_html(root), "<div><b></b></div>");
+	assert!(LEAVES.with_borrow(|x| !x.contains_key(&leaving.as_entity())));
+}
@@ -0,0 +1,391 @@
+//! a scrolling list that only builds the rows that are visible
+//!
+//! Rows are built once and then reused for other items as the list scrolls, so a row reads its item from signals:
+//!
+//! ```ignore
+//! VirtualList::new(users.signal_vec_cloned(), RowHeight::Fixed(32.), |row: &Row<User>| e::div()
+//!     .text_signal(row.item_signal().map(|x| x.name))
+//! )
+//!     .class(css::class!(css::height!(400 px)))
+//! ```
+//!
+//! Rows that aren't visible are replaced by spacers above and below the visible ones.
+
+use crate::{prelude::*, element::SignalHandlesCollection};
+use futures_signals::{
+	signal::{Mutable, MutableSignal, MutableSignalCloned, Signal, SignalExt},
+	signal_vec::{SignalVec, SignalVecExt, VecDiff},
+};
+use std::{cell::RefCell, collections::VecDeque, rc::Rc};
+
+// rows built when the size of the list isn't known, e.g. on the server or before it's attached
+const FALLBACK_ROWS: usize = 20;
+
+/// How tall the rows of a `VirtualList` are, in pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RowHeight {
+	/// Every row is this tall
+	Fixed(f64),
+	/// Rows are measured once they're built, until then they're assumed to be this tall
+	Measured(f64),
+}
+
+/// What a row of a `VirtualList` shows, it's reused for other items as the list scrolls
+pub struct Row<T> {
+	index: Mutable<usize>,
+	item: Mutable<T>,
+}
+
+impl<T: Clone> Row<T> {
+	pub fn index(&self) -> usize { self.index.get() }
+	pub fn index_signal(&self) -> MutableSignal<usize> { self.index.signal() }
+	pub fn item(&self) -> T { self.item.get_cloned() }
+	pub fn item_signal(&self) -> MutableSignalCloned<T> { self.item.signal_cloned() }
+}
+
+struct Slot {
+	index: usize,
+	element: Element,
+	// shows the item at an index
+	bind: Box<dyn Fn(usize)>,
+}
+
+struct Core {
+	list: Entity,
+	top: Element,
+	bottom: Element,
+	len: usize,
+	row_height: RowHeight,
+	// only for `RowHeight::Measured`
+	heights: Vec<Option<f64>>,
+	overscan: usize,
+	// in order, right after `top`
+	slots: VecDeque<Slot>,
+	build: Box<dyn Fn(usize) -> Slot>,
+}
+
+impl Core {
+	fn height_of(&self, index: usize) -> f64 {
+		match self.row_height {
+			RowHeight::Fixed(height) => height,
+			RowHeight::Measured(estimate) => self.heights.get(index).copied().flatten().unwrap_or(estimate),
+		}
+	}
+
+	fn offset_of(&self, index: usize) -> f64 {
+		match self.row_height {
+			RowHeight::Fixed(height) => index as f64 * height,
+			RowHeight::Measured(_) => (0..index).map(|i| self.height_of(i)).sum(),
+		}
+	}
+
+	// the row at `y`, `len` if it's past the end
+	fn index_at(&self, y: f64) -> usize {
+		match self.row_height {
+			RowHeight::Fixed(height) if height > 0. => ((y / height).max(0.) as usize).min(self.len),
+			RowHeight::Fixed(_) => 0,
+			RowHeight::Measured(_) => {
+				let mut offset = 0.;
+				for i in 0..self.len {
+					offset += self.height_of(i);
+					if offset > y { return i; }
+				}
+				self.len
+			},
+		}
+	}
+
+	// scroll position and height, unknown until the list is attached and laid out
+	fn viewport(&self) -> Option<(f64, f64)> {
+		if crate::ssr::ENABLED { return None; }
+		let element = self.list.get_cmp::<web_sys::Element>();
+		let height = element.client_height();
+		(element.is_connected() && height > 0).then(|| (element.scroll_top() as f64, height as f64))
+	}
+
+	fn scroll_to(&self, y: f64) {
+		if crate::ssr::ENABLED { return; }
+		self.list.get_cmp::<web_sys::Element>().set_scroll_top(y.round() as i32);
+	}
+
+	fn range(&self) -> (usize, usize) {
+		let Some((top, height)) = self.viewport() else { return (0, self.len.min(FALLBACK_ROWS)); };
+		let start = self.index_at(top).saturating_sub(self.overscan);
+		let end = (self.index_at(top + height) + 1 + self.overscan).min(self.len);
+		(start.min(end), end)
+	}
+
+	fn insert_slot(&mut self, position: usize, slot: Slot, front: bool) {
+		// `top` is the first child
+		Element(self.list).add_child_at(position + 1, slot.element);
+		if front { self.slots.push_front(slot); } else { self.slots.push_back(slot); }
+	}
+
+	fn reuse_or_build(&self, free: &mut Vec<Slot>, index: usize) -> Slot {
+		let Some(mut slot) = free.pop() else { return (self.build)(index); };
+		slot.element.leave_parent();
+		slot.index = index;
+		(slot.bind)(index);
+		slot
+	}
+
+	// rows that are still in the range are kept, the rest are reused for the new ones
+	fn set_range(&mut self, start: usize, end: usize) {
+		let (old_start, old_end) = self.slots.front().zip(self.slots.back()).map_or((0, 0), |(first, last)| (first.index, last.index + 1));
+
+		if start >= old_end || end <= old_start {
+			// nothing in common, so the rows are rebound in place
+			while self.slots.len() > end - start { if let Some(slot) = self.slots.pop_back() { slot.element.remove(); } }
+			for (i, slot) in self.slots.iter_mut().enumerate() {
+				slot.index = start + i;
+				(slot.bind)(start + i);
+			}
+			while self.slots.len() < end - start {
+				let slot = (self.build)(start + self.slots.len());
+				self.insert_slot(self.slots.len(), slot, false);
+			}
+			return;
+		}
+
+		let mut free = Vec::new();
+		while self.slots.front().is_some_and(|x| x.index < start) { free.extend(self.slots.pop_front()); }
+		while self.slots.back().is_some_and(|x| x.index >= end) { free.extend(self.slots.pop_back()); }
+		for index in (start..old_start).rev() {
+			let slot = self.reuse_or_build(&mut free, index);
+			self.insert_slot(0, slot, true);
+		}
+		for index in old_end.max(start)..end {
+			let slot = self.reuse_or_build(&mut free, index);
+			self.insert_slot(self.slots.len(), slot, false);
+		}
+		for slot in free { slot.element.remove(); }
+	}
+
+	// whether any height changed
+	fn measure(&mut self) -> bool {
+		if crate::ssr::ENABLED || !matches!(self.row_height, RowHeight::Measured(_)) { return false; }
+		let mut changed = false;
+		for slot in &self.slots {
+			let height = slot.element.get_cmp::<web_sys::Element>().unchecked_ref::<web_sys::HtmlElement>().offset_height() as f64;
+			if height > 0. && self.heights[slot.index] != Some(height) {
+				self.heights[slot.index] = Some(height);
+				changed = true;
+			}
+		}
+		changed
+	}
+
+	fn update_spacers(&self) {
+		let (start, end) = self.slots.front().zip(self.slots.back()).map_or((self.len, self.len), |(first, last)| (first.index, last.index + 1));
+		let total = self.offset_of(self.len);
+		self.top.set_style(css::height::px(self.offset_of(start)));
+		self.bottom.set_style(css::height::px(total - self.offset_of(end)));
+	}
+
+	fn render(&mut self) {
+		// measured rows above the visible ones move them, so the scroll position follows the first visible row
+		let anchor = self.viewport().map(|(top, _)| { let index = self.index_at(top); (index, self.offset_of(index) - top) });
+
+		// measuring changes how many rows fit, so the range is adjusted a few times at most
+		for _ in 0..3 {
+			let (start, end) = self.range();
+			self.set_range(start, end);
+			if !self.measure() { break; }
+		}
+		self.update_spacers();
+
+		if let Some((index, delta)) = anchor.filter(|&(index, _)| index < self.len) {
+			let top = self.offset_of(index) - delta;
+			if self.viewport().is_some_and(|(current, _)| (current - top).abs() >= 1.) { self.scroll_to(top); }
+		}
+	}
+
+	fn rebind(&self) {
+		for slot in &self.slots { (slot.bind)(slot.index); }
+	}
+
+	// scrolls by `height` if a row of that height was inserted or removed above the visible ones
+	fn keep_in_place(&self, index: usize, height: f64) {
+		let Some((top, _)) = self.viewport() else { return; };
+		if index < self.index_at(top) { self.scroll_to(top + height); }
+	}
+}
+
+// re-renders when the list is resized
+struct Observer {
+	observer: web_sys::ResizeObserver,
+	_callback: Closure<dyn FnMut()>,
+}
+
+impl Drop for Observer {
+	fn drop(&mut self) { self.observer.disconnect(); }
+}
+
+/// A scrolling list that only keeps the rows in view as entities, plus `overscan` rows on either side
+///
+/// The list scrolls on its own, so it needs a height, e.g. from a class.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, AsElement)]
+pub struct VirtualList(pub Entity);
+
+struct State(Rc<RefCell<Core>>);
+
+impl VirtualList {
+	fn with_build(row_height: RowHeight, build: Box<dyn Fn(usize) -> Slot>) -> (Self, Rc<RefCell<Core>>) {
+		let list = crate::create::div()
+			.class(css::class!(css::overflow_y::auto, css::overflow_anchor::none));
+		let top = crate::create::div().as_element();
+		let bottom = crate::create::div().as_element();
+		list.add_child(top);
+		list.add_child(bottom);
+
+		let core = Rc::new(RefCell::new(Core {
+			list: list.as_entity(),
+			top,
+			bottom,
+			len: 0,
+			row_height,
+			heights: Vec::new(),
+			overscan: 3,
+			slots: VecDeque::new(),
+			build,
+		}));
+		list.add_component(State(Rc::clone(&core)));
+
+		if !crate::ssr::ENABLED {
+			list.add_on_scroll({ let core = Rc::clone(&core); move |_| core.borrow_mut().render() });
+			let callback = Closure::<dyn FnMut()>::new({ let core = Rc::clone(&core); move || core.borrow_mut().render() });
+			let observer = web_sys::ResizeObserver::new(callback.as_ref().unchecked_ref()).expect("can't create resize observer");
+			observer.observe(&list.get_cmp::<web_sys::Element>());
+			list.add_component(Observer { observer, _callback: callback });
+		}
+
+		(Self(list.as_entity()), core)
+	}
+
+	/// A row for each item of `items`, `signal_vec::always` makes a `SignalVec` out of a `Vec`
+	pub fn new<T, E>(items: impl SignalVec<Item = T> + 'static, row_height: RowHeight, row: impl Fn(&Row<T>) -> E + 'static) -> Self where
+		T: Clone + 'static,
+		E: AsElement,
+	{
+		let values = Rc::new(RefCell::new(Vec::<T>::new()));
+		let build = Box::new({
+			let values = Rc::clone(&values);
+			move |index: usize| {
+				let handle = Row { index: Mutable::new(index), item: Mutable::new(values.borrow()[index].clone()) };
+				let element = row(&handle).as_element();
+				let values = Rc::clone(&values);
+				Slot { index, element, bind: Box::new(move |index| {
+					handle.index.set_neq(index);
+					handle.item.set(values.borrow()[index].clone());
+				}) }
+			}
+		});
+		let (list, core) = Self::with_build(row_height, build);
+
+		let handle = items.for_each(move |diff| {
+			let measured = matches!(core.borrow().row_height, RowHeight::Measured(_));
+			// rows inserted or removed above the visible ones would push the visible rows around otherwise
+			let (mut inserted, mut removed) = (None, None);
+			{
+				let mut values = values.borrow_mut();
+				let mut core = core.borrow_mut();
+				match diff {
+					VecDiff::Replace { values: new } => { *values = new; core.heights.clear(); },
+					VecDiff::InsertAt { index, value } => { values.insert(index, value); if measured { core.heights.insert(index, None); } inserted = Some(index); },
+					VecDiff::Push { value } => values.push(value),
+					VecDiff::UpdateAt { index, value } => values[index] = value,
+					VecDiff::RemoveAt { index } => {
+						removed = Some((index, core.height_of(index)));
+						values.remove(index);
+						if measured { core.heights.remove(index); }
+					},
+					VecDiff::Pop {} => { values.pop(); },
+					VecDiff::Move { old_index, new_index } => {
+						let value = values.remove(old_index);
+						values.insert(new_index, value);
+						if measured { let height = core.heights.remove(old_index); core.heights.insert(new_index, height); }
+					},
+					VecDiff::Clear {} => values.clear(),
+				}
+				core.len = values.len();
+				if measured { core.heights.resize(values.len(), None); }
+			}
+
+			let mut core = core.borrow_mut();
+			let shifted = inserted.map(|index| (index, core.height_of(index))).or(removed.map(|(index, height)| (index, -height)));
+			core.render();
+			// rows whose index didn't change might show a different item now
+			core.rebind();
+			if let Some((index, height)) = shifted { core.keep_in_place(index, height); }
+			core.render();
+			std::future::ready(())
+		});
+		list.spawn(handle);
+		list
+	}
+
+	/// `count` rows that only know their index
+	pub fn with_count<E: AsElement>(count: impl Signal<Item = usize> + 'static, row_height: RowHeight, row: impl Fn(&Row<usize>) -> E + 'static) -> Self {
+		let build = Box::new(move |index: usize| {
+			let handle = Row { index: Mutable::new(index), item: Mutable::new(index) };
+			let element = row(&handle).as_element();
+			Slot { index, element, bind: Box::new(move |index| {
+				handle.index.set_neq(index);
+				handle.item.set_neq(index);
+			}) }
+		});
+		let (list, core) = Self::with_build(row_height, build);
+
+		let handle = count.for_each(move |count| {
+			let mut core = core.borrow_mut();
+			core.len = count;
+			if matches!(core.row_height, RowHeight::Measured(_)) { core.heights.resize(count, None); }
+			core.render();
+			std::future::ready(())
+		});
+		list.spawn(handle);
+		list
+	}
+
+	fn spawn(self, fut: impl std::future::Future<Output = ()> + 'static) {
+		let (handle, fut) = futures_signals::cancelable_future(fut, Default::default);
+		crate::signals_ext::spawn(fut);
+		self.get_cmp_mut_or_default::<SignalHandlesCollection>().0.push(handle);
+	}
+
+	/// How many rows beyond the visible ones are kept on either side, 3 by default
+	pub fn set_overscan(&self, rows: usize) {
+		let core = Rc::clone(&self.get_cmp::<State>().0);
+		let mut core = core.borrow_mut();
+		core.overscan = rows;
+		core.render();
+	}
+	#[must_use] pub fn overscan(self, rows: usize) -> Self { self.set_overscan(rows); self }
+
+	/// Scroll so that the row at `index` is at the top
+	pub fn scroll_to_index(&self, index: usize) {
+		let core = Rc::clone(&self.get_cmp::<State>().0);
+		let mut core = core.borrow_mut();
+		let top = core.offset_of(index.min(core.len));
+		core.scroll_to(top);
+		core.render();
+	}
+}
+
+#[test]
+fn renders_a_window() {
+	use futures_signals::signal_vec::MutableVec;
+
+	let items = MutableVec::new_with_values((0..1000).collect::<Vec<u32>>());
+	let list = VirtualList::new(items.signal_vec(), RowHeight::Fixed(10.), |row: &Row<u32>| crate::create::span().text_signal(row.item_signal().map(|x| x.to_string())))
+		.overscan(0);
+	crate::ssr::run_pending();
+	assert_eq!(list.get_cmp::<Children>().len(), FALLBACK_ROWS + 2);
+	let html = crate::ssr::render_html(list);
+	assert!(html.contains("<span>0</span><span>1</span>"));
+	assert!(html.ends_with("<span>19</span><div style=\"height:9800px;\"></div></div>"), "{html}");
+
+	items.lock_mut().insert(0, 42);
+	crate::ssr::run_pending();
+	assert!(crate::ssr::render_html(list).contains("<span>42</span><span>0</span>"));
+}
@@ -7,25 +7,25 @@
 
 #[doc(inline)] pub use crate::create::strings::*;
 
-// interning disabled in debug mode to help track memory leaks
+// interning disabled in debug mode to help track memory leaks, and outside of wasm where there is nothing to intern into
 macro_rules! intern_strings {
 	() => {};
 	($name:ident, $s:expr; $($rest:tt)*) => {
 		pub fn $name() -> &'static str {
-			#[cfg(debug_assertions)]
+			#[cfg(any(debug_assertions, not(target_arch = "wasm32")))]
 			{$s}
 
-			#[cfg(not(debug_assertions))]
+			#[cfg(all(not(debug_assertions), target_arch = "wasm32"))]
 			{wasm_bindgen::intern($s)}
 		}
 		intern_strings! {$($rest)*}
 	};
 	($name:ident; $($rest:tt)*) => {
 		pub fn $name() -> &'static str {
-			#[cfg(debug_assertions)]
+			#[cfg(any(debug_assertions, not(target_arch = "wasm32")))]
 			{stringify!($name)}
 
-			#[cfg(not(debug_assertions))]
+			#[cfg(all(not(debug_assertions), target_arch = "wasm32"))]
 			{wasm_bindgen::intern(stringify!($name))}
 		}
 		intern_strings! {$($rest)*}
@@ -78,8 +78,11 @@ intern_strings! {
 	file;
 	loading; lazy;
 	name; r#for, "for";
+	__hobo_entity;
+}
 
-	// events
+// events
+intern_strings! {
 	click; contextmenu; dblclick; mousedown; mouseenter;
 	mouseleave; mousemove; mouseover; mouseout; mouseup;
 	change; keydown; keyup; scroll; resize; blur; focus;
@@ -87,4 +90,11 @@ intern_strings! {
 	load; canplay; drag; submit; input; animationcancel; animationend;
 	animationiteration; animationstart; popstate; hashchange;
 	cut; copy; paste;
+	pointerdown; pointermove; pointerup; pointercancel; pointerenter;
+	pointerleave; pointerover; pointerout; gotpointercapture; lostpointercapture;
+	transitionstart; transitionend; transitioncancel; transitionrun;
+	dragstart; dragend; dragenter; dragleave; dragover; drop;
+	focusin; focusout; compositionstart; compositionupdate; compositionend;
+	beforeinput; keypress;
+	play; pause; ended; timeupdate; volumechange;
 }
@@ -7,7 +7,6 @@ use crate::{
 	style_storage::{StyleStorage, STYLE_STORAGE},
 	StorageRef, StorageRefMut,
 };
-use once_cell::sync::Lazy;
 use owning_ref::{OwningRef, OwningRefMut};
 use std::{
 	any::TypeId,
@@ -17,8 +16,32 @@ use std::{
 };
 use sugars::hash;
 
+/// A global that every thread has its own value of
+///
+/// A server can render pages on several threads at once, which would race if they shared the ECS.
+/// Values are leaked, so that they can be borrowed for `'static` just like a `static`.
+pub struct PerThread<T: 'static> {
+	key: &'static std::thread::LocalKey<&'static T>,
+}
+
+impl<T> PerThread<T> {
+	pub(crate) const fn new(key: &'static std::thread::LocalKey<&'static T>) -> Self { Self { key } }
+}
+
+impl<T> std::ops::Deref for PerThread<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T { self.key.with(|x| *x) }
+}
+
+thread_local! {
+	static THREAD_WORLD: &'static World = Box::leak(Box::new(new_world()));
+}
+
 #[allow(clippy::redundant_pub_crate)]
-pub(crate) static WORLD: Lazy<World> = Lazy::new(|| {
+pub(crate) static WORLD: PerThread<World> = PerThread::new(&THREAD_WORLD);
+
+fn new_world() -> World {
 	let world = World { next_entity: AtomicU64::new(1), ..Default::default() };
 	world.component_ownership.borrow_mut().insert(Entity::root(), BTreeSet::default());
 
@@ -40,9 +63,14 @@ pub(crate) static WORLD: Lazy<World> = Lazy::new(|| {
 				}
 			}
 
+			let res = res.trim();
+			if crate::ssr::ENABLED {
+				if res.is_empty() { crate::ssr::remove_attr(entity, web_str::class()); } else { crate::ssr::set_attr(entity, web_str::class(), res); }
+				return;
+			}
+
 			let elements = WORLD.storage::<web_sys::Element>();
 			let element = elements.get(entity).unwrap();
-			let res = res.trim();
 			if res.is_empty() {
 				element.remove_attribute(web_str::class()).expect("can't remove class attribute");
 			} else {
@@ -58,7 +86,7 @@ pub(crate) static WORLD: Lazy<World> = Lazy::new(|| {
 	create::register_handlers(&world);
 
 	world
-});
+}
 
 // @Awpteamoose: maybe World doesn't even have to be pub
 #[derive(Default)]
@@ -69,10 +97,6 @@ pub struct World {
 	next_entity: AtomicU64,
 }
 
-// safe because js is single-threaded (for now)
-unsafe impl Send for World {}
-unsafe impl Sync for World {}
-
 impl World {
 	#[cfg(debug_assertions)]
 	#[track_caller]
@@ -228,7 +252,7 @@ impl World {
 
 		let children = self.storage::<Children>().get(entity).map(|x| x.0.clone());
 		if let Some(children) = children {
-			for child in children { self.remove_entity(child); }
+			crate::transition::remove_children(entity, || for child in children { self.remove_entity(child); });
 		}
 
 		let parent = self.storage::<Parent>().get(entity).copied();
@@ -74,6 +74,76 @@ pub fn derive_element(input: proc_macro::TokenStream) -> proc_macro::TokenStream
 	}
 }
 
+#[proc_macro_derive(FormModel, attributes(form))]
+pub fn derive_form_model(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+	let crate_name = crate_name();
+	let input = syn::parse_macro_input!(input as syn::DeriveInput);
+	let name = input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+	let syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(fields), .. }) = input.data else {
+		return syn::Error::new(Span::call_site(), "FormModel can only be derived for structs with named fields").to_compile_error().into();
+	};
+
+	let mut idents = Vec::new();
+	let mut reads = Vec::new();
+	for field in fields.named {
+		let ident = field.ident.expect("named field without a name");
+		let ty = field.ty;
+		let mut control_name = syn::ext::IdentExt::unraw(&ident).to_string();
+		let mut validators = Vec::new();
+		let mut custom = Vec::new();
+		for attr in field.attrs.iter().filter(|x| x.path().is_ident("form")) {
+			let res = attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("name") {
+					control_name = meta.value()?.parse::<syn::LitStr>()?.value();
+				} else if meta.path.is_ident("required") {
+					validators.push(quote! { #crate_name::form::Validator::Required });
+				} else if meta.path.is_ident("min_length") {
+					let x: syn::LitInt = meta.value()?.parse()?;
+					validators.push(quote! { #crate_name::form::Validator::MinLength(#x) });
+				} else if meta.path.is_ident("max_length") {
+					let x: syn::LitInt = meta.value()?.parse()?;
+					validators.push(quote! { #crate_name::form::Validator::MaxLength(#x) });
+				} else if meta.path.is_ident("pattern") {
+					let x: syn::LitStr = meta.value()?.parse()?;
+					validators.push(quote! { #crate_name::form::Validator::Pattern(#x) });
+				} else if meta.path.is_ident("validate") {
+					// a function or a closure
+					custom.push(meta.value()?.parse::<syn::Expr>()?);
+				} else {
+					return Err(meta.error("expected one of `name`, `required`, `min_length`, `max_length`, `pattern`, `validate`"));
+				}
+				Ok(())
+			});
+			if let Err(e) = res { return e.to_compile_error().into(); }
+		}
+
+		reads.push(quote! {
+			let #ident = #crate_name::form::read_field::<#ty>(get, #control_name, &[#(#validators),*], &mut errors);
+			#(#crate_name::form::validate_field(#ident.as_ref(), #control_name, #custom, &mut errors);)*
+		});
+		idents.push(ident);
+	}
+
+	// every `None` has its error recorded, so this only fails if there are errors anyway
+	let unwrap = (!idents.is_empty()).then(|| quote! {
+		let (#(Some(#idents),)*) = (#(#idents,)*) else { return Err(errors); };
+	});
+
+	(quote! {
+		impl #impl_generics #crate_name::form::FormModel for #name #ty_generics #where_clause {
+			fn from_values(get: &dyn Fn(&str) -> Option<String>) -> Result<Self, #crate_name::form::FieldErrors> {
+				let mut errors = #crate_name::form::FieldErrors::default();
+				#(#reads)*
+				if !errors.is_empty() { return Err(errors); }
+				#unwrap
+				Ok(Self { #(#idents),* })
+			}
+		}
+	}).into()
+}
+
 // fn extract_element_type(data: &syn::Data) -> syn::Type {
 //     match data {
 //         syn::Data::Struct(syn::DataStruct { fields: syn::Fields::Named(syn::FieldsNamed { named, .. }), .. }) => {